@@ -0,0 +1,8 @@
+#!/bin/sh
+#Replaces the bundled ROM database with the latest chip-8-database, which is MIT licensed
+set -e
+cd "$(dirname "$0")"
+url=https://raw.githubusercontent.com/chip-8/chip-8-database/master/database
+for file in programs.json sha1-hashes.json; do
+    curl -fsSL "$url/$file" -o "$file"
+done
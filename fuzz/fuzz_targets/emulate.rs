@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+//The emulator is a binary crate, so the core gets compiled straight from its source
+#[path = "../../src/chip8/mod.rs"]
+#[allow(dead_code, unused_imports)]
+mod chip8;
+
+fuzz_target!(|data: &[u8]| chip8::fuzz::run(data));
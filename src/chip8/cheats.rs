@@ -0,0 +1,122 @@
+use super::Chip8;
+use std::fmt;
+
+//A byte the frontend writes every frame. With a compare value it only replaces that value, like
+//the compare byte of a Game Genie code, otherwise the byte stays frozen.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+    pub name: String,
+}
+
+impl Cheat {
+    //Hex codes like "2F0:05" to freeze and "2F0:05:03" to patch, followed by an optional name
+    pub fn parse(text: &str) -> Option<Cheat> {
+        let mut parts = text.trim().splitn(2, char::is_whitespace);
+        let mut fields = parts.next()?.split(':');
+        let address = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let value = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let compare = match fields.next() {
+            Some(compare) => Some(u8::from_str_radix(compare, 16).ok()?),
+            None => None,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Cheat {
+            address,
+            value,
+            compare,
+            name: parts.next().unwrap_or("").trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:03X}:{:02X}", self.address, self.value)?;
+        if let Some(compare) = self.compare {
+            write!(f, ":{:02X}", compare)?;
+        }
+        if !self.name.is_empty() {
+            write!(f, " {}", self.name)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    //One cheat per line, comments start with ; or #
+    pub fn parse(text: &str) -> Result<Cheats, String> {
+        let mut cheats = Cheats::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split([';', '#']).next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let cheat = Cheat::parse(line)
+                .ok_or_else(|| format!("line {}: invalid cheat {}", number + 1, line))?;
+            cheats.add(cheat);
+        }
+        Ok(cheats)
+    }
+
+    //The format parse reads
+    pub fn to_text(&self) -> String {
+        self.cheats
+            .iter()
+            .map(|cheat| format!("{}\n", cheat))
+            .collect()
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        if index < self.cheats.len() {
+            Some(self.cheats.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+}
+
+impl Chip8 {
+    //Called by the frontends once per frame. Cheats may write anywhere in RAM, also into the
+    //interpreter, addresses the machine doesn't have are skipped.
+    pub fn apply_cheats(&mut self) {
+        let ram_size = self.machine.ram_size.min(self.memory.len());
+        for cheat in self.cheats.iter() {
+            let address = cheat.address as usize;
+            if address >= ram_size {
+                continue;
+            }
+            if cheat
+                .compare
+                .is_none_or(|compare| self.memory[address] == compare)
+            {
+                self.memory[address] = cheat.value;
+            }
+        }
+    }
+}
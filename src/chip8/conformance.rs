@@ -0,0 +1,214 @@
+use super::{Chip8, Chip8Error, Quirks, Scheduler};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//Runs test ROMs headlessly and compares the final screen against golden images in tests/golden.
+//The suite ROMs (Timendus' chip8-test-suite) aren't checked in, put them into tests/roms and run
+//the ignored tests with cargo test -- --ignored.
+//Set CHIP8_BLESS=1 to write missing or changed golden images instead of failing. Blessed images
+//only record what the emulator draws, check them by hand before committing them.
+//The ROMs run at the default speed of the frontends, so they see as many instructions per frame.
+
+const PLATFORMS: [&str; 4] = ["default", "chip8", "schip", "xochip"];
+
+struct SuiteRom {
+    file: &'static str,
+    frames: usize,
+    //Written to 0x1FF before starting, the suite uses it to skip its menus
+    select: fn(&str) -> Option<u8>,
+    //The key held down in a frame
+    key: fn(usize) -> Option<u8>,
+}
+
+const SUITE: [SuiteRom; 6] = [
+    SuiteRom {
+        file: "1-chip8-logo.ch8",
+        frames: 40,
+        select: |_| None,
+        key: |_| None,
+    },
+    SuiteRom {
+        file: "2-ibm-logo.ch8",
+        frames: 40,
+        select: |_| None,
+        key: |_| None,
+    },
+    SuiteRom {
+        file: "3-corax+.ch8",
+        frames: 60,
+        select: |_| None,
+        key: |_| None,
+    },
+    SuiteRom {
+        file: "4-flags.ch8",
+        frames: 100,
+        select: |_| None,
+        key: |_| None,
+    },
+    SuiteRom {
+        file: "5-quirks.ch8",
+        frames: 600,
+        select: |platform| match platform {
+            "chip8" => Some(1),
+            "schip" => Some(2),
+            "xochip" => Some(3),
+            _ => None,
+        },
+        key: |_| None,
+    },
+    //The EX9E test lights up the keys that are held, press each one for a few frames
+    SuiteRom {
+        file: "6-keypad.ch8",
+        frames: 90,
+        select: |_| Some(1),
+        key: |frame| match frame {
+            10..=73 => Some(((frame - 10) / 4) as u8),
+            _ => None,
+        },
+    },
+];
+
+//Draws the font, adds with carry, shifts and draws a sprite over the corner so every platform looks different
+//Its goldens are checked by hand: BCD 000 or 064 depending on the shift, a carry of 1 and the zero
+//in the corner clipped or wrapped
+const HANDMADE_ROM: [u8; 92] = [
+    0x00, 0xE0, 0x60, 0x00, 0x61, 0x00, 0x62, 0x01, //Clear, v0 = digit, v1 = x, v2 = y
+    0xF0, 0x29, 0xD1, 0x25, 0x70, 0x01, 0x71, 0x06, //Draw digit v0 and move right
+    0x30, 0x08, 0x12, 0x08, 0x61, 0x00, 0x62, 0x08, //First row 0-7, then next row
+    0xF0, 0x29, 0xD1, 0x25, 0x70, 0x01, 0x71, 0x06, //
+    0x30, 0x10, 0x12, 0x18, 0x63, 0xFF, 0x64, 0x02, //Second row 8-F, then v3 = 255, v4 = 2
+    0x83, 0x44, 0x85, 0xF0, 0x66, 0x81, 0x67, 0x01, //v3 += v4, v5 = carry, v6 = 0x81, v7 = 1
+    0x86, 0x76, 0xA3, 0x00, 0xF6, 0x33, 0xF2, 0x65, //Shift, BCD of v6 into v0-v2
+    0x68, 0x10, 0x69, 0x00, 0xF0, 0x29, 0xD9, 0x85, //Draw the three BCD digits and the carry
+    0x79, 0x06, 0xF1, 0x29, 0xD9, 0x85, 0x79, 0x06, //
+    0xF2, 0x29, 0xD9, 0x85, 0x79, 0x06, 0xF5, 0x29, //
+    0xD9, 0x85, 0x6A, 0x3D, 0x6B, 0x1C, 0xA0, 0x00, //Draw a zero at 61,28 to wrap or clip it
+    0xDA, 0xB5, 0x12, 0x5A, //Loop forever
+];
+
+fn run_rom(
+    rom: &[u8],
+    quirks: Quirks,
+    frames: usize,
+    select: Option<u8>,
+    key: fn(usize) -> Option<u8>,
+) -> Result<Chip8, Chip8Error> {
+    let mut chip = Chip8::from_rom(rom.to_vec())?;
+    chip.quirks = quirks;
+    if let Some(select) = select {
+        chip.memory[0x1FF] = select;
+    }
+    let mut scheduler = Scheduler::with_speed(Scheduler::DEFAULT_SPEED);
+    for frame in 0..frames {
+        chip.key_pressed = [false; 16];
+        if let Some(key) = key(frame) {
+            chip.key_pressed[key as usize] = true;
+        }
+        while !scheduler.step(&mut chip)?.frame_ended {}
+    }
+    Ok(chip)
+}
+
+fn check_run(name: &str, run: Result<Chip8, Chip8Error>) -> Option<String> {
+    match run {
+        Ok(chip) => check_golden(name, &to_pbm(&chip)),
+        Err(e) => Some(format!("stopped with {}", e)),
+    }
+}
+
+//Plain PBM so the golden images open in any image viewer and diff nicely
+fn to_pbm(chip: &Chip8) -> String {
+    let mut pbm = format!("P1\n{} {}\n", chip.gfx.width(), chip.gfx.height());
+    for row in chip.gfx.rows() {
+        let line: Vec<&str> = row.iter().map(|&p| if p { "1" } else { "0" }).collect();
+        pbm.push_str(&line.join(" "));
+        pbm.push('\n');
+    }
+    pbm
+}
+
+fn test_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+//Returns None if the screen matches, otherwise why it doesn't
+fn check_golden(name: &str, pbm: &str) -> Option<String> {
+    let path = test_dir("golden").join(format!("{}.pbm", name));
+    let bless = env::var_os("CHIP8_BLESS").is_some();
+
+    match fs::read_to_string(&path) {
+        Ok(golden) if golden == pbm => None,
+        _ if bless => {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, pbm).unwrap();
+            None
+        }
+        Ok(_) => Some(format!("screen differs from {}", path.display())),
+        Err(_) => Some(format!("{} is missing", path.display())),
+    }
+}
+
+fn report(results: &[(String, &str, Option<String>)]) {
+    for platform in PLATFORMS.iter() {
+        let runs: Vec<_> = results.iter().filter(|(_, p, _)| p == platform).collect();
+        let passed = runs
+            .iter()
+            .filter(|(_, _, failure)| failure.is_none())
+            .count();
+        println!("{}: {}/{} passed", platform, passed, runs.len());
+        for (name, _, failure) in runs.iter() {
+            if let Some(failure) = failure {
+                println!("  {} failed: {}", name, failure);
+            }
+        }
+    }
+    assert!(
+        results.iter().all(|(_, _, failure)| failure.is_none()),
+        "conformance failures"
+    );
+}
+
+#[test]
+fn test_handmade_rom_conformance() {
+    let results: Vec<_> = PLATFORMS
+        .iter()
+        .map(|&platform| {
+            let run = run_rom(
+                &HANDMADE_ROM,
+                Quirks::from_name(platform).unwrap(),
+                40,
+                None,
+                |_| None,
+            );
+            let name = format!("handmade_{}", platform);
+            let failure = check_run(&name, run);
+            (name, platform, failure)
+        })
+        .collect();
+    report(&results);
+}
+
+#[test]
+#[ignore = "needs the chip8-test-suite ROMs in tests/roms"]
+fn test_suite_conformance() {
+    let mut results = Vec::new();
+    for rom in SUITE.iter() {
+        let data = fs::read(test_dir("roms").join(rom.file));
+        for &platform in PLATFORMS.iter() {
+            let name = format!("{}_{}", rom.file.trim_end_matches(".ch8"), platform);
+            let failure = match &data {
+                Ok(data) => {
+                    let quirks = Quirks::from_name(platform).unwrap();
+                    let select = (rom.select)(platform);
+                    check_run(&name, run_rom(data, quirks, rom.frames, select, rom.key))
+                }
+                Err(e) => Some(format!("could not read tests/roms/{}: {}", rom.file, e)),
+            };
+            results.push((name, platform, failure));
+        }
+    }
+    report(&results);
+}
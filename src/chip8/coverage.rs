@@ -0,0 +1,81 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Access {
+    Execute,
+    Read,
+    Write,
+}
+
+//How often every byte of memory was executed, read as data and written
+#[derive(Clone, PartialEq, Debug)]
+pub struct Coverage {
+    pub executed: Vec<u32>,
+    pub read: Vec<u32>,
+    pub written: Vec<u32>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage {
+            executed: vec![0; 4096],
+            read: vec![0; 4096],
+            written: vec![0; 4096],
+        }
+    }
+
+    pub fn record(&mut self, range: Range<usize>, access: Access) {
+        let counts = match access {
+            Access::Execute => &mut self.executed,
+            Access::Read => &mut self.read,
+            Access::Write => &mut self.written,
+        };
+        for count in counts[range].iter_mut() {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        let pairs = [
+            (&mut self.executed, &other.executed),
+            (&mut self.read, &other.read),
+            (&mut self.written, &other.written),
+        ];
+        for (counts, other_counts) in pairs {
+            for (count, other_count) in counts.iter_mut().zip(other_counts.iter()) {
+                *count = count.saturating_add(*other_count);
+            }
+        }
+    }
+
+    //Executed, read and written counts after each other as little endian u32
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.executed
+            .iter()
+            .chain(self.read.iter())
+            .chain(self.written.iter())
+            .flat_map(|count| count.to_le_bytes())
+            .collect()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Coverage> {
+        if data.len() != 3 * 4096 * 4 {
+            return None;
+        }
+        let counts: Vec<u32> = data
+            .chunks(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        Some(Coverage {
+            executed: counts[..4096].to_vec(),
+            read: counts[4096..8192].to_vec(),
+            written: counts[8192..].to_vec(),
+        })
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Coverage {
+        Coverage::new()
+    }
+}
@@ -0,0 +1,87 @@
+use super::{Chip8, Chip8Error};
+
+//The register file as a debugger sees it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i_reg: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+//Where the last DXYN took its sprite from and where it was drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpriteDraw {
+    pub address: u16,
+    pub height: u8,
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Chip8 {
+    pub fn registers(&self) -> Registers {
+        Registers {
+            v: self.v,
+            i_reg: self.i_reg,
+            pc: self.pc,
+            sp: self.sp.min(u8::MAX as usize) as u8,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        }
+    }
+
+    //Deeper stacks than sp can show keep their depth as long as sp isn't changed
+    pub fn set_registers(&mut self, registers: &Registers) -> Result<(), Chip8Error> {
+        let sp = if registers.sp == self.registers().sp {
+            self.sp
+        } else {
+            registers.sp as usize
+        };
+        if self.machine.stack_depth.is_some_and(|depth| sp > depth) {
+            return Err(Chip8Error::StackOverflow);
+        }
+        if sp > self.stack.len() {
+            self.stack.resize(sp, 0);
+        }
+        self.v = registers.v;
+        self.i_reg = registers.i_reg;
+        self.pc = registers.pc;
+        self.sp = sp;
+        self.delay_timer = registers.delay_timer;
+        self.sound_timer = registers.sound_timer;
+        Ok(())
+    }
+
+    pub fn read_memory(&self, address: u16, len: usize) -> Result<&[u8], Chip8Error> {
+        Ok(&self.memory[self.memory_range(address, len)?])
+    }
+
+    pub fn write_memory(&mut self, address: u16, data: &[u8]) -> Result<(), Chip8Error> {
+        let range = self.memory_range(address, data.len())?;
+        self.memory[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    //Where each active subroutine returns to, outermost first
+    pub fn call_stack(&self) -> Vec<u16> {
+        self.stack[..self.sp]
+            .iter()
+            .map(|&call| call.wrapping_add(2))
+            .collect()
+    }
+
+    //call_stack as label+offset, plain addresses without symbols
+    pub fn call_stack_labels(&self) -> Vec<String> {
+        self.call_stack()
+            .into_iter()
+            .map(|address| self.symbols.describe(address))
+            .collect()
+    }
+
+    //Breakpoints are checked by whoever drives the chip, emulate_cycle ignores them
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+    }
+}
@@ -0,0 +1,144 @@
+use super::lockstep::{Core, Lockstep, MachineState};
+use super::{Chip8, Chip8Error, Quirks};
+use proptest::prelude::*;
+
+//Written from the instruction reference and not from the interpreter, only knows the
+//instructions the generated programs use. Behaves like the default quirks.
+struct Reference {
+    state: MachineState,
+}
+
+impl Reference {
+    fn new(start: MachineState) -> Reference {
+        Reference { state: start }
+    }
+}
+
+impl Core for Reference {
+    fn step(&mut self, _keys: &[bool; 16]) -> Result<(), Chip8Error> {
+        let s = &mut self.state;
+        let pc = s.pc as usize;
+        let opcode = (s.memory[pc] as u16) << 8 | s.memory[pc + 1] as u16;
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let nn = (opcode & 0xFF) as u8;
+        let (vx, vy) = (s.v[x], s.v[y]);
+        let mut skip = false;
+
+        match opcode >> 12 {
+            0x3 => skip = vx == nn,
+            0x4 => skip = vx != nn,
+            0x5 => skip = vx == vy,
+            0x6 => s.v[x] = nn,
+            0x7 => s.v[x] = vx.wrapping_add(nn),
+            0x8 => match opcode & 0xF {
+                0x0 => s.v[x] = vy,
+                0x1 => s.v[x] = vx | vy,
+                0x2 => s.v[x] = vx & vy,
+                0x3 => s.v[x] = vx ^ vy,
+                0x4 => {
+                    s.v[x] = vx.wrapping_add(vy);
+                    s.v[0xF] = (vx as u16 + vy as u16 > 0xFF) as u8;
+                }
+                0x5 => {
+                    s.v[x] = vx.wrapping_sub(vy);
+                    s.v[0xF] = (vx >= vy) as u8;
+                }
+                //Shifts set the flag first, so 8FF6 and 8FFE keep the shifted value
+                0x6 => {
+                    s.v[0xF] = vx & 1;
+                    s.v[x] = vx >> 1;
+                }
+                0x7 => {
+                    s.v[x] = vy.wrapping_sub(vx);
+                    s.v[0xF] = (vy >= vx) as u8;
+                }
+                0xE => {
+                    s.v[0xF] = vx >> 7;
+                    s.v[x] = vx << 1;
+                }
+                _ => unreachable!(),
+            },
+            0x9 => skip = vx != vy,
+            0xA => s.i_reg = opcode & 0xFFF,
+            _ => {}
+        }
+        s.pc += if skip { 4 } else { 2 };
+        Ok(())
+    }
+
+    fn state(&self) -> MachineState {
+        self.state.clone()
+    }
+}
+
+fn register() -> impl Strategy<Value = u16> {
+    0..16u16
+}
+
+fn alu_opcode() -> impl Strategy<Value = u16> {
+    prop_oneof![
+        (register(), any::<u8>()).prop_map(|(x, nn)| 0x3000 | x << 8 | nn as u16),
+        (register(), any::<u8>()).prop_map(|(x, nn)| 0x4000 | x << 8 | nn as u16),
+        (register(), register()).prop_map(|(x, y)| 0x5000 | x << 8 | y << 4),
+        (register(), any::<u8>()).prop_map(|(x, nn)| 0x6000 | x << 8 | nn as u16),
+        (register(), any::<u8>()).prop_map(|(x, nn)| 0x7000 | x << 8 | nn as u16),
+        (
+            register(),
+            register(),
+            prop::sample::select(vec![0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE])
+        )
+            .prop_map(|(x, y, n)| 0x8000 | x << 8 | y << 4 | n),
+        (register(), register()).prop_map(|(x, y)| 0x9000 | x << 8 | y << 4),
+        (0..0x1000u16).prop_map(|nnn| 0xA000 | nnn),
+    ]
+}
+
+fn to_rom(program: &[u16]) -> Vec<u8> {
+    program.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+#[test]
+fn test_divergence_is_reported() {
+    //8XY6 shifts VX or VY depending on the quirks
+    let rom = to_rom(&[0x6081, 0x6101, 0x8016]);
+    let mut right = Chip8::from_rom(rom.clone()).unwrap();
+    right.quirks = Quirks::CHIP8;
+    let mut lockstep = Lockstep::new(Chip8::from_rom(rom).unwrap(), right);
+
+    let divergence = lockstep.run(3, &[false; 16]).unwrap_err();
+    assert_eq!(divergence.cycle, 3);
+    assert_eq!(divergence.location, "V0");
+    assert_eq!(divergence.left.v[0], 0x40);
+    assert_eq!(divergence.right.v[0], 0x00);
+}
+
+proptest! {
+    #[test]
+    fn test_interpreter_matches_reference(program in prop::collection::vec(alu_opcode(), 1..64)) {
+        let chip = Chip8::from_rom(to_rom(&program)).unwrap();
+        let reference = Reference::new(chip.state());
+        let mut lockstep = Lockstep::new(chip, reference);
+
+        let result = lockstep.run(program.len() as u64, &[false; 16]);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn test_same_seed_never_diverges(
+        program in prop::collection::vec(
+            prop_oneof![alu_opcode(), (register(), any::<u8>()).prop_map(|(x, nn)| 0xC000 | x << 8 | nn as u16)],
+            1..64
+        ),
+        seed in any::<u64>()
+    ) {
+        let mut left = Chip8::from_rom(to_rom(&program)).unwrap();
+        let mut right = Chip8::from_rom(to_rom(&program)).unwrap();
+        left.seed_rng(seed);
+        right.seed_rng(seed);
+        let mut lockstep = Lockstep::new(left, right);
+
+        let result = lockstep.run(program.len() as u64, &[false; 16]);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+}
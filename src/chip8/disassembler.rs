@@ -0,0 +1,138 @@
+use super::symbols::Symbols;
+
+//Mnemonics as in Cowgod's Chip-8 technical reference, unknown opcodes are shown as data
+pub fn disassemble(opcode: u16) -> String {
+    let nnn = opcode & 0x0FFF;
+    let nn = opcode & 0x00FF;
+    let n = opcode & 0x000F;
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+
+    match (opcode & 0xF000) >> 12 {
+        0x0 => match opcode {
+            0x00E0 => "CLS".to_string(),
+            0x00EE => "RET".to_string(),
+            0x00FE => "LOW".to_string(),
+            0x00FF => "HIGH".to_string(),
+            _ => format!("SYS 0x{:03X}", nnn),
+        },
+        0x1 => format!("JP 0x{:03X}", nnn),
+        0x2 => format!("CALL 0x{:03X}", nnn),
+        0x3 => format!("SE V{:X}, 0x{:02X}", x, nn),
+        0x4 => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        0x5 if n == 0 => format!("SE V{:X}, V{:X}", x, y),
+        0x6 => format!("LD V{:X}, 0x{:02X}", x, nn),
+        0x7 => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        0x8 => {
+            let mnemonic = match n {
+                0x0 => "LD",
+                0x1 => "OR",
+                0x2 => "AND",
+                0x3 => "XOR",
+                0x4 => "ADD",
+                0x5 => "SUB",
+                0x6 => "SHR",
+                0x7 => "SUBN",
+                0xE => "SHL",
+                _ => return data(opcode),
+            };
+            format!("{} V{:X}, V{:X}", mnemonic, x, y)
+        }
+        0x9 if n == 0 => format!("SNE V{:X}, V{:X}", x, y),
+        0xA => format!("LD I, 0x{:03X}", nnn),
+        0xB => format!("JP V0, 0x{:03X}", nnn),
+        0xC => format!("RND V{:X}, 0x{:02X}", x, nn),
+        0xD => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        0xE if nn == 0x9E => format!("SKP V{:X}", x),
+        0xE if nn == 0xA1 => format!("SKNP V{:X}", x),
+        0xF => match nn {
+            0x07 => format!("LD V{:X}, DT", x),
+            0x0A => format!("LD V{:X}, K", x),
+            0x15 => format!("LD DT, V{:X}", x),
+            0x18 => format!("LD ST, V{:X}", x),
+            0x1E => format!("ADD I, V{:X}", x),
+            0x29 => format!("LD F, V{:X}", x),
+            0x30 => format!("LD HF, V{:X}", x),
+            0x33 => format!("LD B, V{:X}", x),
+            0x55 => format!("LD [I], V{:X}", x),
+            0x65 => format!("LD V{:X}, [I]", x),
+            _ => data(opcode),
+        },
+        _ => data(opcode),
+    }
+}
+
+//Jump, call and I targets shown as label+offset where the symbols know one
+pub fn disassemble_with(opcode: u16, symbols: &Symbols) -> String {
+    let text = disassemble(opcode);
+    let nnn = opcode & 0x0FFF;
+    let has_target = match opcode >> 12 {
+        0x0 => !matches!(opcode, 0x00E0 | 0x00EE | 0x00FE | 0x00FF),
+        0x1 | 0x2 | 0xA | 0xB => true,
+        _ => false,
+    };
+    match symbols.resolve(nnn) {
+        Some(label) if has_target => text.replace(&format!("0x{:03X}", nnn), &label),
+        _ => text,
+    }
+}
+
+fn data(opcode: u16) -> String {
+    format!("DW 0x{:04X}", opcode)
+}
+
+//The opcode pattern an instruction belongs to, like DXYN, for counting instruction types
+pub fn pattern(opcode: u16) -> &'static str {
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+
+    match (opcode & 0xF000) >> 12 {
+        0x0 => match opcode {
+            0x00E0 => "00E0",
+            0x00EE => "00EE",
+            0x00FE => "00FE",
+            0x00FF => "00FF",
+            _ => "0NNN",
+        },
+        0x1 => "1NNN",
+        0x2 => "2NNN",
+        0x3 => "3XNN",
+        0x4 => "4XNN",
+        0x5 if n == 0 => "5XY0",
+        0x6 => "6XNN",
+        0x7 => "7XNN",
+        0x8 => match n {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "????",
+        },
+        0x9 if n == 0 => "9XY0",
+        0xA => "ANNN",
+        0xB => "BNNN",
+        0xC => "CXNN",
+        0xD => "DXYN",
+        0xE if nn == 0x9E => "EX9E",
+        0xE if nn == 0xA1 => "EXA1",
+        0xF => match nn {
+            0x07 => "FX07",
+            0x0A => "FX0A",
+            0x15 => "FX15",
+            0x18 => "FX18",
+            0x1E => "FX1E",
+            0x29 => "FX29",
+            0x30 => "FX30",
+            0x33 => "FX33",
+            0x55 => "FX55",
+            0x65 => "FX65",
+            _ => "????",
+        },
+        _ => "????",
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::{Index, IndexMut};
+
+//What DXYN draws on. The SCHIP switches between 64x32 and 128x64 pixels, in low resolution only
+//the top left of the pixels is used.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Display {
+    hires: bool,
+    pixels: [[bool; Display::MAX_WIDTH]; Display::MAX_HEIGHT],
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display {
+            hires: false,
+            pixels: [[false; Display::MAX_WIDTH]; Display::MAX_HEIGHT],
+        }
+    }
+}
+
+impl Display {
+    pub const LORES_WIDTH: usize = 64;
+    pub const LORES_HEIGHT: usize = 32;
+    pub const MAX_WIDTH: usize = 128;
+    pub const MAX_HEIGHT: usize = 64;
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            Display::MAX_WIDTH
+        } else {
+            Display::LORES_WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            Display::MAX_HEIGHT
+        } else {
+            Display::LORES_HEIGHT
+        }
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    //Switching clears the screen like XO-CHIP does
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels = [[false; Display::MAX_WIDTH]; Display::MAX_HEIGHT];
+    }
+
+    //The rows of the current resolution, each as wide as the screen
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        let width = self.width();
+        self.pixels[..self.height()]
+            .iter()
+            .map(move |row| &row[..width])
+    }
+}
+
+impl Index<usize> for Display {
+    type Output = [bool; Display::MAX_WIDTH];
+
+    fn index(&self, y: usize) -> &Self::Output {
+        &self.pixels[y]
+    }
+}
+
+impl IndexMut<usize> for Display {
+    fn index_mut(&mut self, y: usize) -> &mut Self::Output {
+        &mut self.pixels[y]
+    }
+}
@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+//Everything a ROM can do wrong, the interpreter stops instead of panicking
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Chip8Error {
+    RomTooLarge(usize),
+    AddressOutOfRange(u16),
+    ReservedWrite(u16),
+    StackOverflow,
+    StackUnderflow,
+    ReadFailed(io::ErrorKind),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::RomTooLarge(size) => {
+                write!(f, "ROM of {} bytes doesn't fit into memory", size)
+            }
+            Chip8Error::AddressOutOfRange(address) => {
+                write!(f, "address {:04X} is out of memory", address)
+            }
+            Chip8Error::ReservedWrite(address) => {
+                write!(f, "write to the interpreter's memory at {:04X}", address)
+            }
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "return without a subroutine call"),
+            Chip8Error::ReadFailed(kind) => write!(f, "could not read ROM: {:?}", kind),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Chip8Error {
+        Chip8Error::ReadFailed(e.kind())
+    }
+}
@@ -0,0 +1,161 @@
+//Sprites of the hex digits, FX29 points I at the small ones and FX30 at the large ones.
+//The large ones follow the small ones in memory.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Font {
+    pub small: [u8; Font::SMALL_LEN],
+    pub large: [u8; Font::LARGE_LEN],
+}
+
+impl Font {
+    pub const SMALL_HEIGHT: u16 = 5;
+    pub const LARGE_HEIGHT: u16 = 10;
+    pub const SMALL_LEN: usize = 16 * 5;
+    pub const LARGE_LEN: usize = 10 * 10;
+    pub const LEN: usize = Font::SMALL_LEN + Font::LARGE_LEN;
+
+    pub const PRESETS: [(&'static str, Font); 4] = [
+        ("vip", Font::VIP),
+        ("dream6800", Font::DREAM6800),
+        ("eti660", Font::ETI660),
+        ("schip", Font::SCHIP),
+    ];
+
+    //Only the SCHIP had 8x10 digits, the older fonts get its ones so FX30 works with all of them
+    const SCHIP_LARGE: [u8; Font::LARGE_LEN] = [
+        0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, //0
+        0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, //1
+        0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, //2
+        0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, //3
+        0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, //4
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, //5
+        0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, //6
+        0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, //7
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, //8
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, //9
+    ];
+
+    //The interpreter in the ROM of the COSMAC VIP
+    pub const VIP: Font = Font {
+        small: [
+            0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+            0x60, 0x20, 0x20, 0x20, 0x70, //1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+            0xA0, 0xA0, 0xF0, 0x20, 0x20, //4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+            0xF0, 0x10, 0x10, 0x10, 0x10, //7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+            0xF0, 0x50, 0x70, 0x50, 0xF0, //B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+            0xF0, 0x50, 0x50, 0x50, 0xF0, //D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, //F
+        ],
+        large: Font::SCHIP_LARGE,
+    };
+
+    pub const DREAM6800: Font = Font {
+        small: [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0, //0
+            0x40, 0x40, 0x40, 0x40, 0x40, //1
+            0xE0, 0x20, 0xE0, 0x80, 0xE0, //2
+            0xE0, 0x20, 0xE0, 0x20, 0xE0, //3
+            0x80, 0xA0, 0xA0, 0xE0, 0x20, //4
+            0xE0, 0x80, 0xE0, 0x20, 0xE0, //5
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0, //6
+            0xE0, 0x20, 0x20, 0x20, 0x20, //7
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0, //8
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0, //9
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0, //A
+            0xC0, 0xA0, 0xE0, 0xA0, 0xC0, //B
+            0xE0, 0x80, 0x80, 0x80, 0xE0, //C
+            0xC0, 0xA0, 0xA0, 0xA0, 0xC0, //D
+            0xE0, 0x80, 0xE0, 0x80, 0xE0, //E
+            0xE0, 0x80, 0xC0, 0x80, 0x80, //F
+        ],
+        large: Font::SCHIP_LARGE,
+    };
+
+    pub const ETI660: Font = Font {
+        small: [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0, //0
+            0x20, 0x20, 0x20, 0x20, 0x20, //1
+            0xE0, 0x20, 0xE0, 0x80, 0xE0, //2
+            0xE0, 0x20, 0xE0, 0x20, 0xE0, //3
+            0xA0, 0xA0, 0xE0, 0x20, 0x20, //4
+            0xE0, 0x80, 0xE0, 0x20, 0xE0, //5
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0, //6
+            0xE0, 0x20, 0x20, 0x20, 0x20, //7
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0, //8
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0, //9
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0, //A
+            0x80, 0x80, 0xE0, 0xA0, 0xE0, //B
+            0xE0, 0x80, 0x80, 0x80, 0xE0, //C
+            0x20, 0x20, 0xE0, 0xA0, 0xE0, //D
+            0xE0, 0x80, 0xE0, 0x80, 0xE0, //E
+            0xE0, 0x80, 0xC0, 0x80, 0x80, //F
+        ],
+        large: Font::SCHIP_LARGE,
+    };
+
+    //What this emulator always loaded, the small font is the one most interpreters use today
+    pub const SCHIP: Font = Font {
+        small: [
+            0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+            0x20, 0x60, 0x20, 0x20, 0x70, //1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+            0x90, 0x90, 0xF0, 0x10, 0x10, //4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+            0xF0, 0x10, 0x20, 0x40, 0x40, //7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+            0xE0, 0x90, 0xE0, 0x90, 0xE0, //B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+            0xE0, 0x90, 0x90, 0x90, 0xE0, //D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, //F
+        ],
+        large: Font::SCHIP_LARGE,
+    };
+
+    pub fn from_name(name: &str) -> Option<Font> {
+        Font::PRESETS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, font)| *font)
+    }
+
+    //A font file holds the 80 bytes of the small digits, optionally followed by the 100 bytes of
+    //the large ones. Without them the SCHIP ones are used.
+    pub fn from_bytes(data: &[u8]) -> Option<Font> {
+        let mut font = Font::SCHIP;
+        match data.len() {
+            Font::SMALL_LEN => font.small.copy_from_slice(data),
+            Font::LEN => {
+                font.small.copy_from_slice(&data[..Font::SMALL_LEN]);
+                font.large.copy_from_slice(&data[Font::SMALL_LEN..]);
+            }
+            _ => return None,
+        }
+        Some(font)
+    }
+
+    pub fn to_bytes(self) -> [u8; Font::LEN] {
+        let mut bytes = [0; Font::LEN];
+        bytes[..Font::SMALL_LEN].copy_from_slice(&self.small);
+        bytes[Font::SMALL_LEN..].copy_from_slice(&self.large);
+        bytes
+    }
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::SCHIP
+    }
+}
@@ -0,0 +1,80 @@
+use super::Chip8;
+
+//Shared by the cargo fuzz target in fuzz/ and the offline run below.
+//Input: 2 bytes big endian ROM length, the ROM, then 2 bytes of held keys for every 100 cycles.
+
+pub const MAX_CYCLES: usize = 10_000;
+const CYCLES_PER_KEY_STATE: usize = 100;
+
+//Errors are fine, only panics are failures
+pub fn run(data: &[u8]) {
+    if data.len() < 2 {
+        return;
+    }
+    let rom_len = (data[0] as usize) << 8 | data[1] as usize;
+    let rom_end = (2 + rom_len).min(data.len());
+    let rom = data[2..rom_end].to_vec();
+    let key_states: Vec<u16> = data[rom_end..]
+        .chunks(2)
+        .map(|c| (c[0] as u16) << 8 | *c.get(1).unwrap_or(&0) as u16)
+        .collect();
+
+    let mut chip = match Chip8::from_rom(rom) {
+        Ok(chip) => chip,
+        Err(_) => return,
+    };
+    chip.seed_rng(0);
+
+    for cycle in 0..MAX_CYCLES {
+        let keys = key_states
+            .get(cycle / CYCLES_PER_KEY_STATE)
+            .copied()
+            .unwrap_or(0);
+        for (i, key) in chip.key_pressed.iter_mut().enumerate() {
+            *key = keys & (1 << i) != 0;
+        }
+        if chip.emulate_cycle().is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::fs;
+    use std::panic;
+    use std::path::Path;
+
+    const RANDOM_INPUTS: usize = 500;
+
+    fn run_without_panic(name: &str, data: &[u8]) {
+        if panic::catch_unwind(|| run(data)).is_err() {
+            let hex: Vec<String> = data.iter().map(|b| format!("{:02X}", b)).collect();
+            panic!("{} panicked, input: {}", name, hex.join(""));
+        }
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/emulate");
+        for entry in fs::read_dir(corpus).unwrap() {
+            let path = entry.unwrap().path();
+            run_without_panic(&path.display().to_string(), &fs::read(&path).unwrap());
+        }
+    }
+
+    //Random programs mostly hit the interesting opcodes quickly since every 16 bit word is valid
+    #[test]
+    fn test_fuzz_random() {
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+        for i in 0..RANDOM_INPUTS {
+            let rom_len = rng.gen_range(0, 256);
+            let mut data = vec![0, rom_len as u8];
+            data.extend((0..rom_len + 64).map(|_| rng.gen::<u8>()));
+            run_without_panic(&format!("random input {}", i), &data);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Chip8, Chip8Error, Display};
+use std::fmt;
+
+//Everything two interpreters have to agree on after each instruction
+#[derive(Clone, PartialEq, Debug)]
+pub struct MachineState {
+    pub pc: u16,
+    pub i_reg: u16,
+    pub v: [u8; 16],
+    //Only the active entries, from the outermost call on
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub memory: [u8; 4096],
+    pub gfx: Display,
+}
+
+//An interpreter that can be run against another one
+pub trait Core {
+    fn step(&mut self, keys: &[bool; 16]) -> Result<(), Chip8Error>;
+    fn state(&self) -> MachineState;
+}
+
+impl Core for Chip8 {
+    fn step(&mut self, keys: &[bool; 16]) -> Result<(), Chip8Error> {
+        self.key_pressed = *keys;
+        self.emulate_cycle()
+    }
+
+    fn state(&self) -> MachineState {
+        MachineState {
+            pc: self.pc,
+            i_reg: self.i_reg,
+            v: self.v,
+            stack: self.stack[..self.sp].to_vec(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            memory: self.memory,
+            gfx: self.gfx,
+        }
+    }
+}
+
+pub struct Divergence {
+    pub cycle: u64,
+    pub location: String,
+    pub left: Box<MachineState>,
+    pub right: Box<MachineState>,
+}
+
+//Runs two cores with the same keys and stops at the first difference.
+//Seed both with Chip8::seed_rng so CXNN doesn't diverge on its own.
+pub struct Lockstep<L: Core, R: Core> {
+    pub left: L,
+    pub right: R,
+    pub cycle: u64,
+}
+
+impl<L: Core, R: Core> Lockstep<L, R> {
+    pub fn new(left: L, right: R) -> Lockstep<L, R> {
+        Lockstep {
+            left,
+            right,
+            cycle: 0,
+        }
+    }
+
+    pub fn step(&mut self, keys: &[bool; 16]) -> Result<(), Divergence> {
+        let left_result = self.left.step(keys);
+        let right_result = self.right.step(keys);
+        self.cycle += 1;
+
+        let left = self.left.state();
+        let right = self.right.state();
+        let difference = if left_result != right_result {
+            Some(format!(
+                "outcome ({} vs {})",
+                describe(left_result),
+                describe(right_result)
+            ))
+        } else {
+            first_difference(&left, &right)
+        };
+        match difference {
+            Some(location) => Err(Divergence {
+                cycle: self.cycle,
+                location,
+                left: Box::new(left),
+                right: Box::new(right),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn run(&mut self, cycles: u64, keys: &[bool; 16]) -> Result<(), Divergence> {
+        for _ in 0..cycles {
+            self.step(keys)?;
+        }
+        Ok(())
+    }
+}
+
+fn describe(result: Result<(), Chip8Error>) -> String {
+    match result {
+        Ok(()) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn first_difference(left: &MachineState, right: &MachineState) -> Option<String> {
+    if left.pc != right.pc {
+        return Some("PC".to_string());
+    }
+    if left.i_reg != right.i_reg {
+        return Some("I".to_string());
+    }
+    if let Some(i) = (0..16).find(|&i| left.v[i] != right.v[i]) {
+        return Some(format!("V{:X}", i));
+    }
+    if left.stack != right.stack {
+        return Some("stack".to_string());
+    }
+    if left.delay_timer != right.delay_timer || left.sound_timer != right.sound_timer {
+        return Some("timers".to_string());
+    }
+    if let Some(address) = (0..4096).find(|&a| left.memory[a] != right.memory[a]) {
+        return Some(format!(
+            "memory at {:03X} ({:02X} vs {:02X})",
+            address, left.memory[address], right.memory[address]
+        ));
+    }
+    if left.gfx.is_hires() != right.gfx.is_hires() {
+        return Some("resolution".to_string());
+    }
+    for (y, (left_row, right_row)) in left.gfx.rows().zip(right.gfx.rows()).enumerate() {
+        if let Some(x) = (0..left_row.len()).find(|&x| left_row[x] != right_row[x]) {
+            return Some(format!("pixel {},{}", x, y));
+        }
+    }
+    None
+}
+
+impl fmt::Display for MachineState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registers: Vec<String> = self.v.iter().map(|v| format!("{:02X}", v)).collect();
+        let stack: Vec<String> = self
+            .stack
+            .iter()
+            .map(|address| format!("{:03X}", address))
+            .collect();
+        write!(
+            f,
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X} V={} stack=[{}]",
+            self.pc,
+            self.i_reg,
+            self.delay_timer,
+            self.sound_timer,
+            registers.join(" "),
+            stack.join(" ")
+        )
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Diverged in {} after cycle {}",
+            self.location, self.cycle
+        )?;
+        writeln!(f, "left:  {}", self.left)?;
+        write!(f, "right: {}", self.right)
+    }
+}
+
+impl fmt::Debug for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
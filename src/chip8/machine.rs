@@ -0,0 +1,81 @@
+use std::ops::Range;
+
+//Memory layout of the computer the interpreter ran on
+#[derive(Clone, PartialEq, Debug)]
+pub struct Machine {
+    //Where the ROM gets copied to and where execution starts, usually the same
+    pub load_address: u16,
+    pub entry_point: u16,
+    //Addresses from here on don't exist, at most 4096
+    pub ram_size: usize,
+    //Nested subroutine calls before the stack overflows, None for no limit at all since the stack
+    //doesn't live in emulated memory
+    pub stack_depth: Option<usize>,
+    //Memory of the interpreter itself, the program may read but not write it
+    pub reserved: Vec<Range<u16>>,
+}
+
+impl Machine {
+    pub const NAMES: [&'static str; 4] = ["default", "vip", "vip2k", "eti660"];
+    pub const MAX_RAM_SIZE: usize = 4096;
+
+    pub fn from_name(name: &str) -> Option<Machine> {
+        let machine = match name {
+            "default" => Machine::default(),
+            //Interpreter below 0x200, its variables, stack and the display buffer at the top
+            "vip" => Machine {
+                stack_depth: Some(12),
+                reserved: vec![0x000..0x200, 0xEA0..0x1000],
+                ..Machine::default()
+            },
+            "vip2k" => Machine {
+                ram_size: 2048,
+                stack_depth: Some(12),
+                reserved: vec![0x000..0x200, 0x6A0..0x800],
+                ..Machine::default()
+            },
+            "eti660" => Machine {
+                load_address: 0x600,
+                entry_point: 0x600,
+                reserved: vec![0x000..0x600],
+                ..Machine::default()
+            },
+            _ => return None,
+        };
+        Some(machine)
+    }
+
+    //Bytes from the load address to the end of RAM or the interpreter memory above it
+    pub fn max_rom_size(&self) -> usize {
+        let start = self.load_address as usize;
+        if self.is_reserved(&(start..start + 1)) {
+            return 0;
+        }
+        let end = self
+            .reserved
+            .iter()
+            .map(|r| r.start as usize)
+            .filter(|&reserved| reserved > start)
+            .fold(self.ram_size.min(Machine::MAX_RAM_SIZE), usize::min);
+        end.saturating_sub(start)
+    }
+
+    pub fn is_reserved(&self, range: &Range<usize>) -> bool {
+        self.reserved
+            .iter()
+            .any(|r| range.start < r.end as usize && (r.start as usize) < range.end)
+    }
+}
+
+//What this emulator always emulated, 4 KiB that all belong to the program
+impl Default for Machine {
+    fn default() -> Machine {
+        Machine {
+            load_address: 0x200,
+            entry_point: 0x200,
+            ram_size: Machine::MAX_RAM_SIZE,
+            stack_depth: Some(16),
+            reserved: Vec::new(),
+        }
+    }
+}
@@ -1,67 +1,229 @@
+mod cheats;
+mod coverage;
+mod debug;
+mod disassembler;
+mod display;
+mod error;
+mod font;
+#[cfg(any(test, fuzzing))]
+pub mod fuzz;
+mod lockstep;
+mod machine;
+mod quirks;
+mod save_state;
+mod search;
+mod symbols;
+mod timing;
+mod trace;
+
+pub use self::cheats::{Cheat, Cheats};
+pub use self::coverage::{Access, Coverage};
+pub use self::debug::{Registers, SpriteDraw};
+pub use self::disassembler::{disassemble, disassemble_with, pattern};
+pub use self::display::Display;
+pub use self::error::Chip8Error;
+pub use self::font::Font;
+pub use self::lockstep::{Core, Lockstep, MachineState};
+pub use self::machine::Machine;
+pub use self::quirks::Quirks;
+pub use self::search::{Comparison, MemorySearch};
+pub use self::symbols::Symbols;
+pub use self::timing::{Scheduler, Timing};
+pub use self::trace::{TraceEntry, Tracer};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::prelude::*;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 pub struct Chip8 {
     pub screen_scale: u32,
-    pub gfx: [[bool; Chip8::SCREEN_WIDTH]; Chip8::SCREEN_HEIGHT],
+    pub quirks: Quirks,
+    pub timing: Timing,
+    machine: Machine,
+    pub rom_hash: String,
+    rom_len: usize,
+    pub cycles: u64,
+    pub tracer: Option<Box<dyn Tracer>>,
+    pub coverage: Option<Arc<Mutex<Coverage>>>,
+    pub breakpoints: BTreeSet<u16>,
+    pub symbols: Symbols,
+    pub cheats: Cheats,
+    pub last_sprite: Option<SpriteDraw>,
+    //What the last instruction wrote to memory, for hooks that watch it
+    pub last_write: Option<Range<u16>>,
+    //A DXYN waiting with the display_wait quirk and whether its frame came
+    waiting_for_vblank: bool,
+    vblank: bool,
+    rng: StdRng,
+    pub gfx: Display,
     pub key_pressed: [bool; 16],
     memory: [u8; 4096],
     v: [u8; 16],
-    stack: [u16; 16],
+    //Grows up to the stack depth of the machine, entries above sp are stale
+    stack: Vec<u16>,
     opcode: u16,
     pc: u16,
-    sp: u8,
+    sp: usize,
     i_reg: u16,
+    font_address: u16,
     delay_timer: u8,
     sound_timer: u8,
 }
 
 impl Chip8 {
-    pub const SCREEN_WIDTH: usize = 64;
-    pub const SCREEN_HEIGHT: usize = 32;
+    pub const SCREEN_WIDTH: usize = Display::LORES_WIDTH;
+    pub const SCREEN_HEIGHT: usize = Display::LORES_HEIGHT;
     const GFX_BITMASK: [u8; 8] = [128, 64, 32, 16, 8, 4, 2, 1];
 
     fn new() -> Chip8 {
-        let gfx = [[false; Chip8::SCREEN_WIDTH]; Chip8::SCREEN_HEIGHT];
+        let gfx = Display::default();
         let mut memory = [0; 4096]; //4096 bits of memory
-        memory = Chip8::load_hex_digits(memory);
+        memory[..Font::LEN].copy_from_slice(&Font::default().to_bytes());
 
         Chip8 {
             gfx,
             key_pressed: [false; 16], //16 Keys
             memory,
-            v: [0; 16],     //CPU registers named V0 to VE, last register is the carry flag
-            stack: [0; 16], //16 Stacklevels
+            v: [0; 16], //CPU registers named V0 to VE, last register is the carry flag
+            stack: Vec::new(),
             opcode: 0,
             i_reg: 0,
+            font_address: 0,
             pc: 0x200, //Execution must start at 0x200
             sp: 0,
             delay_timer: 0,
             sound_timer: 0,
             screen_scale: 1,
+            quirks: Quirks::default(),
+            timing: Timing::default(),
+            machine: Machine::default(),
+            rom_hash: String::new(),
+            rom_len: 0,
+            cycles: 0,
+            tracer: None,
+            coverage: None,
+            breakpoints: BTreeSet::new(),
+            symbols: Symbols::default(),
+            cheats: Cheats::default(),
+            last_sprite: None,
+            last_write: None,
+            waiting_for_vblank: false,
+            vblank: false,
+            rng: StdRng::from_entropy(),
         }
     }
 
-    pub fn create_chip(file: File, screen_scale: u32) -> Chip8 {
-        let mut chip = Chip8::load_program(file);
+    pub fn create_chip(
+        file: File,
+        screen_scale: u32,
+        machine: Machine,
+    ) -> Result<Chip8, Chip8Error> {
+        let mut chip = Chip8::load_program(file, machine)?;
         chip.screen_scale = screen_scale;
-        chip
+        Ok(chip)
     }
 
-    fn load_program(mut file: File) -> Chip8 {
-        let mut chip8 = Chip8::new();
+    fn load_program(mut file: File, machine: Machine) -> Result<Chip8, Chip8Error> {
         let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        chip8.load_into_memory(buffer);
-        chip8
+        file.read_to_end(&mut buffer)?;
+        Chip8::with_machine(machine, buffer)
+    }
+
+    #[cfg(any(test, fuzzing))]
+    pub fn from_rom(data: Vec<u8>) -> Result<Chip8, Chip8Error> {
+        Chip8::with_machine(Machine::default(), data)
+    }
+
+    pub fn with_machine(machine: Machine, data: Vec<u8>) -> Result<Chip8, Chip8Error> {
+        let mut chip8 = Chip8::new();
+        chip8.pc = machine.entry_point;
+        chip8.machine = machine;
+        chip8.rom_hash = Chip8::hash_rom(&data);
+        chip8.load_into_memory(data)?;
+        Ok(chip8)
+    }
+
+    //Makes CXNN repeatable, two chips with the same seed draw the same numbers
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    //Lowercase hex SHA-1 like the chip-8-database uses to identify ROMs
+    pub fn hash_rom(data: &[u8]) -> String {
+        sha1::Sha1::from(data).digest().to_string()
+    }
+
+    //The ROM has to fit between the load address and the end of RAM without covering the
+    //interpreter
+    pub fn load_into_memory(&mut self, data: Vec<u8>) -> Result<(), Chip8Error> {
+        let range = self
+            .memory_range(self.machine.load_address, data.len())
+            .map_err(|_| Chip8Error::RomTooLarge(data.len()))?;
+        if self.machine.is_reserved(&range) {
+            return Err(Chip8Error::RomTooLarge(data.len()));
+        }
+        self.memory[range].copy_from_slice(&data);
+        self.rom_len = data.len();
+        Ok(())
+    }
+
+    //Replaces the font, FX29 and FX30 then point into the new one
+    pub fn load_font(&mut self, font: &Font, address: u16) -> Result<(), Chip8Error> {
+        let range = self.memory_range(address, Font::LEN)?;
+        //A ROM loaded over the old font keeps its bytes
+        let load_address = self.machine.load_address as usize;
+        let program = load_address..load_address + self.rom_len;
+        let old = self.font_address as usize;
+        for address in (old..old + Font::LEN).filter(|a| !program.contains(a)) {
+            self.memory[address] = 0;
+        }
+        self.memory[range].copy_from_slice(&font.to_bytes());
+        self.font_address = address;
+        Ok(())
     }
 
-    pub fn load_into_memory(&mut self, data: Vec<u8>) {
-        self.memory[0x200..data.len() + 0x200].copy_from_slice(&data);
+    pub fn font_address(&self) -> u16 {
+        self.font_address
     }
 
-    pub fn emulate_cycle(&mut self) {
-        let opcode_upper_8bit = (self.memory[self.pc as usize] as u16) << 8;
-        let opcode_lower_8bit = self.memory[self.pc as usize + 1] as u16;
+    //Memory from start on for len bytes, if all of it exists
+    fn memory_range(&self, start: u16, len: usize) -> Result<Range<usize>, Chip8Error> {
+        let range = start as usize..start as usize + len;
+        if range.end > self.machine.ram_size.min(self.memory.len()) {
+            return Err(Chip8Error::AddressOutOfRange(start));
+        }
+        Ok(range)
+    }
+
+    //Like memory_range, but the program must not write into the interpreter
+    fn writable_range(&self, start: u16, len: usize) -> Result<Range<usize>, Chip8Error> {
+        let range = self.memory_range(start, len)?;
+        if self.machine.is_reserved(&range) {
+            return Err(Chip8Error::ReservedWrite(start));
+        }
+        Ok(range)
+    }
+
+    fn cover(&self, range: Range<usize>, access: Access) {
+        if let Some(coverage) = &self.coverage {
+            coverage.lock().unwrap().record(range, access);
+        }
+    }
+
+    fn wrote(&mut self, range: Range<usize>) {
+        self.cover(range.clone(), Access::Write);
+        self.last_write = Some(range.start as u16..range.end as u16);
+    }
+
+    //On an error nothing is changed and the same instruction fails again on the next cycle
+    pub fn emulate_cycle(&mut self) -> Result<(), Chip8Error> {
+        self.last_write = None;
+        let pc = self.pc;
+        let opcode_range = self.memory_range(pc, 2)?;
+        let opcode_upper_8bit = (self.memory[opcode_range.start] as u16) << 8;
+        let opcode_lower_8bit = self.memory[opcode_range.start + 1] as u16;
         self.opcode = opcode_upper_8bit | opcode_lower_8bit;
 
         let instruction = (&self.opcode & 0xF000) >> 12;
@@ -70,11 +232,12 @@ impl Chip8 {
         let n = (self.opcode & 0x000F) as u8;
         let y = ((self.opcode & 0x00F0) >> 4) as usize;
         let x = ((self.opcode & 0x0F00) >> 8) as usize;
+        let mut stalled = false;
 
         match instruction {
-            0x0 => self.opcode0(nn),
+            0x0 => self.opcode0(nn)?,
             0x1 => self.jump(nnn),
-            0x2 => self.call(nnn),
+            0x2 => self.call(nnn)?,
             0x3 => self.se(self.v[x], nn),
             0x4 => self.sne(self.v[x], nn),
             0x5 => self.se(self.v[x], self.v[y]),
@@ -83,14 +246,47 @@ impl Chip8 {
             0x8 => self.opcode8(n, x, y),
             0x9 => self.sne(self.v[x], self.v[y]),
             0xA => self.ldi(nnn),
-            0xB => self.jump(nnn + self.v[0] as u16),
-            0xC => self.ld(x, rand::random::<u8>() & nn),
-            0xD => self.display_sprite(x, y, n as usize),
+            0xB => {
+                let offset = if self.quirks.jump_uses_vx {
+                    self.v[x]
+                } else {
+                    self.v[0]
+                };
+                self.jump(nnn + offset as u16)
+            }
+            0xC => {
+                let random = self.rng.gen::<u8>();
+                self.ld(x, random & nn)
+            }
+            0xD => stalled = !self.display_sprite(x, y, n as usize)?,
             0xE => self.opcodee(x, nn),
-            0xF => self.opcodef(x, y, n),
+            0xF => self.opcodef(x, y, n)?,
             _ => unreachable!(),
         }
 
+        if self.timing == Timing::Instructions {
+            self.tick_timers();
+        }
+        //A stalled DXYN is counted and traced once, when it draws
+        if stalled {
+            return Ok(());
+        }
+
+        self.cover(opcode_range, Access::Execute);
+        self.cycles += 1;
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.trace(&TraceEntry {
+                cycle: self.cycles,
+                pc,
+                opcode: self.opcode,
+                v: self.v,
+                i_reg: self.i_reg,
+            });
+        }
+        Ok(())
+    }
+
+    fn tick_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
@@ -101,24 +297,49 @@ impl Chip8 {
         }
     }
 
-    fn opcode0(&mut self, subcode: u8) {
-        if subcode == 0xE0 {
-            self.gfx = [[false; 64]; 32];
-        } else if subcode == 0xEE {
-            self.sp -= 1;
-            self.pc = self.stack[self.sp as usize];
+    pub fn sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    fn opcode0(&mut self, subcode: u8) -> Result<(), Chip8Error> {
+        match subcode {
+            0xE0 => self.gfx.clear(),
+            0xEE => {
+                if self.sp == 0 {
+                    return Err(Chip8Error::StackUnderflow);
+                }
+                self.sp -= 1;
+                self.pc = self.stack[self.sp];
+            }
+            //The SCHIP's low and high resolution
+            0xFE => self.gfx.set_hires(false),
+            0xFF => self.gfx.set_hires(true),
+            _ => {}
         }
         self.pc += 2;
+        Ok(())
     }
 
     fn jump(&mut self, location: u16) {
         self.pc = location;
     }
 
-    fn call(&mut self, location: u16) {
-        self.stack[self.sp as usize] = self.pc;
+    fn call(&mut self, location: u16) -> Result<(), Chip8Error> {
+        if self
+            .machine
+            .stack_depth
+            .is_some_and(|depth| self.sp >= depth)
+        {
+            return Err(Chip8Error::StackOverflow);
+        }
+        if self.sp == self.stack.len() {
+            self.stack.push(self.pc);
+        } else {
+            self.stack[self.sp] = self.pc;
+        }
         self.sp += 1;
         self.pc = location;
+        Ok(())
     }
 
     fn se(&mut self, x: u8, y: u8) {
@@ -142,9 +363,18 @@ impl Chip8 {
     fn opcode8(&mut self, subcode: u8, x: usize, y: usize) {
         match subcode {
             0x0 => self.v[x] = self.v[y],
-            0x1 => self.v[x] |= self.v[y],
-            0x2 => self.v[x] &= self.v[y],
-            0x3 => self.v[x] ^= self.v[y],
+            0x1 => {
+                self.v[x] |= self.v[y];
+                self.reset_vf_after_logic();
+            }
+            0x2 => {
+                self.v[x] &= self.v[y];
+                self.reset_vf_after_logic();
+            }
+            0x3 => {
+                self.v[x] ^= self.v[y];
+                self.reset_vf_after_logic();
+            }
             0x4 => {
                 let (reg, overflow_bit) = self.v[x].overflowing_add(self.v[y]);
                 self.v[x] = reg;
@@ -156,8 +386,12 @@ impl Chip8 {
                 self.v[15] = !overflow_bit as u8;
             }
             0x6 => {
-                self.v[15] = self.v[x] & 0b1;
-                self.v[x] >>= 1;
+                let source = if self.quirks.shift_uses_vy {
+                    self.v[y]
+                } else {
+                    self.v[x]
+                };
+                self.write_shift(x, source >> 1, source & 0b1);
             }
             0x7 => {
                 let (reg, overflow_bit) = self.v[y].overflowing_sub(self.v[x]);
@@ -165,34 +399,92 @@ impl Chip8 {
                 self.v[15] = !overflow_bit as u8;
             }
             0xE => {
-                self.v[15] = (self.v[x] & 0b1000_0000) >> 7;
-                self.v[x] <<= 1;
+                let source = if self.quirks.shift_uses_vy {
+                    self.v[y]
+                } else {
+                    self.v[x]
+                };
+                self.write_shift(x, source << 1, (source & 0b1000_0000) >> 7);
             }
             _ => {}
         }
         self.pc += 2;
     }
 
+    //With X=F only the last write stays. The VIP sets the flag last, without the quirk the
+    //shifted value is kept like this emulator always did.
+    fn write_shift(&mut self, x: usize, shifted: u8, flag: u8) {
+        if self.quirks.shift_uses_vy {
+            self.v[x] = shifted;
+            self.v[15] = flag;
+        } else {
+            self.v[15] = flag;
+            self.v[x] = shifted;
+        }
+    }
+
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.v[15] = 0;
+        }
+    }
+
     fn ldi(&mut self, constant: u16) {
         self.i_reg = constant;
         self.pc += 2;
     }
 
-    fn display_sprite(&mut self, x: usize, y: usize, n: usize) {
+    //The frontend or scheduler calls this at the start of every frame
+    pub fn vblank(&mut self) {
+        self.vblank = self.waiting_for_vblank;
+    }
+
+    //False if it stalled instead of drawing
+    fn display_sprite(&mut self, x: usize, y: usize, n: usize) -> Result<bool, Chip8Error> {
+        //Stalls like FX0A, the instruction runs again until the frame begins
+        if self.quirks.display_wait && !self.vblank {
+            self.waiting_for_vblank = true;
+            return Ok(false);
+        }
+        //DXY0 draws a 16x16 sprite of two bytes per row in high resolution
+        let (sprite_width, n) = if n == 0 && self.gfx.is_hires() {
+            (16, 16)
+        } else {
+            (8, n)
+        };
+        let bytes_per_row = sprite_width / 8;
+        let range = self.memory_range(self.i_reg, n * bytes_per_row)?;
+        self.waiting_for_vblank = false;
+        self.vblank = false;
+        self.cover(range.clone(), Access::Read);
+        let bytes = self.memory[range].to_vec();
         self.v[15] = 0; //Set VF to 0 if no pixel gets erased
-        let bytes = self.memory[self.i_reg as usize..self.i_reg as usize + n].to_vec();
 
-        //Modulo with screen size because sprites get wrapped around the display
-        let x_pos: Vec<usize> = (0..8)
-            .map(|a| (self.v[x] as usize + a) % Chip8::SCREEN_WIDTH)
+        //The start position always wraps, the rest of the sprite either wraps or gets clipped
+        let (width, height) = (self.gfx.width(), self.gfx.height());
+        let x_start = self.v[x] as usize % width;
+        let y_start = self.v[y] as usize % height;
+        self.last_sprite = Some(SpriteDraw {
+            address: self.i_reg,
+            height: n as u8,
+            x: x_start as u8,
+            y: y_start as u8,
+        });
+        let clip = self.quirks.clip_sprites;
+        let x_pos: Vec<usize> = (0..sprite_width)
+            .map(|a| x_start + a)
+            .filter(|&a| !clip || a < width)
+            .map(|a| a % width)
             .collect();
         let y_pos: Vec<usize> = (0..n)
-            .map(|a| (self.v[y] as usize + a) % Chip8::SCREEN_HEIGHT)
+            .map(|a| y_start + a)
+            .filter(|&a| !clip || a < height)
+            .map(|a| a % height)
             .collect();
 
-        for (&y, &byte) in y_pos.iter().zip(bytes.iter()) {
-            for (&x, &bit) in x_pos.iter().zip(Chip8::GFX_BITMASK.iter()) {
-                let pixel_set = (byte & bit) != 0;
+        for (&y, row) in y_pos.iter().zip(bytes.chunks(bytes_per_row)) {
+            for (&x, column) in x_pos.iter().zip(0..sprite_width) {
+                let pixel_set = row[column / 8] & Chip8::GFX_BITMASK[column % 8] != 0;
 
                 //If pixel gets erased, set VF to 1
                 if pixel_set && self.gfx[y][x] {
@@ -204,10 +496,12 @@ impl Chip8 {
         }
 
         self.pc += 2;
+        Ok(true)
     }
 
     fn opcodee(&mut self, x: usize, nn: u8) {
-        let reg = self.v[x] as usize;
+        //Only the low nibble names a key
+        let reg = (self.v[x] & 0xF) as usize;
         if nn == 0x9E {
             self.pc += if self.key_pressed[reg] { 4 } else { 2 };
         } else if nn == 0xA1 {
@@ -215,7 +509,7 @@ impl Chip8 {
         }
     }
 
-    fn opcodef(&mut self, x: usize, y: usize, n: u8) {
+    fn opcodef(&mut self, x: usize, y: usize, n: u8) -> Result<(), Chip8Error> {
         match y {
             0x0 => {
                 if n == 0x7 {
@@ -242,133 +536,51 @@ impl Chip8 {
             }
             0x2 => {
                 //Set i to location of sprite in vx
-                //This works because sprites are 5bytes long and begin at memory address 0x0
-                self.i_reg = self.v[x] as u16 * 5;
+                self.i_reg = self.font_address + (self.v[x] & 0xF) as u16 * Font::SMALL_HEIGHT;
+                self.pc += 2;
+            }
+            0x3 if n == 0x0 => {
+                //The large digits of the SCHIP
+                self.i_reg = self.font_address
+                    + Font::SMALL_LEN as u16
+                    + (self.v[x] & 0xF) as u16 * Font::LARGE_HEIGHT;
                 self.pc += 2;
             }
             0x3 => {
-                self.memory[self.i_reg as usize] = self.v[x] / 100;
-                self.memory[self.i_reg as usize + 1] = (self.v[x] / 10) % 10;
-                self.memory[self.i_reg as usize + 2] = (self.v[x] % 100) % 10;
+                let start = self.writable_range(self.i_reg, 3)?.start;
+                self.wrote(start..start + 3);
+                self.memory[start] = self.v[x] / 100;
+                self.memory[start + 1] = (self.v[x] / 10) % 10;
+                self.memory[start + 2] = (self.v[x] % 100) % 10;
                 self.pc += 2;
             }
             0x5 => {
-                for i in 0..=x {
-                    self.memory[self.i_reg as usize + i] = self.v[i];
+                let range = self.writable_range(self.i_reg, x + 1)?;
+                self.wrote(range.clone());
+                self.memory[range].copy_from_slice(&self.v[..=x]);
+                if self.quirks.load_store_increments_i {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
                 self.pc += 2;
             }
             0x6 => {
-                for i in 0..=x {
-                    self.v[i] = self.memory[self.i_reg as usize + i];
+                let range = self.memory_range(self.i_reg, x + 1)?;
+                self.cover(range.clone(), Access::Read);
+                self.v[..=x].copy_from_slice(&self.memory[range]);
+                if self.quirks.load_store_increments_i {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
                 self.pc += 2;
             }
             _ => {}
         }
-    }
-
-    fn load_hex_digits(mut memory: [u8; 4096]) -> [u8; 4096] {
-        //Zero
-        memory[0] = 0xF0;
-        memory[1] = 0x90;
-        memory[2] = 0x90;
-        memory[3] = 0x90;
-        memory[4] = 0xF0;
-        //One
-        memory[5] = 0x20;
-        memory[6] = 0x60;
-        memory[7] = 0x20;
-        memory[8] = 0x20;
-        memory[9] = 0x70;
-        //Two
-        memory[10] = 0xF0;
-        memory[11] = 0x10;
-        memory[12] = 0xF0;
-        memory[13] = 0x80;
-        memory[14] = 0xF0;
-        //Three
-        memory[15] = 0xF0;
-        memory[16] = 0x10;
-        memory[17] = 0xF0;
-        memory[18] = 0x10;
-        memory[19] = 0xF0;
-        //Four
-        memory[20] = 0x90;
-        memory[21] = 0x90;
-        memory[22] = 0xF0;
-        memory[23] = 0x10;
-        memory[24] = 0x10;
-        //Five
-        memory[25] = 0xF0;
-        memory[26] = 0x80;
-        memory[27] = 0xF0;
-        memory[28] = 0x10;
-        memory[29] = 0xF0;
-        //Six
-        memory[30] = 0xF0;
-        memory[31] = 0x80;
-        memory[32] = 0xF0;
-        memory[33] = 0x90;
-        memory[34] = 0xF0;
-        //Seven
-        memory[35] = 0xF0;
-        memory[36] = 0x10;
-        memory[37] = 0x20;
-        memory[38] = 0x40;
-        memory[39] = 0x40;
-        //Eight
-        memory[40] = 0xF0;
-        memory[41] = 0x90;
-        memory[42] = 0xF0;
-        memory[43] = 0x90;
-        memory[44] = 0xF0;
-        //Nine
-        memory[45] = 0xF0;
-        memory[46] = 0x90;
-        memory[47] = 0xF0;
-        memory[48] = 0x10;
-        memory[49] = 0xF0;
-        //A
-        memory[50] = 0xF0;
-        memory[51] = 0x90;
-        memory[52] = 0xF0;
-        memory[53] = 0x90;
-        memory[54] = 0x90;
-        //B
-        memory[55] = 0xE0;
-        memory[56] = 0x90;
-        memory[57] = 0xE0;
-        memory[58] = 0x90;
-        memory[59] = 0xE0;
-        //C
-        memory[60] = 0xF0;
-        memory[61] = 0x80;
-        memory[62] = 0x80;
-        memory[63] = 0x80;
-        memory[64] = 0xF0;
-        //D
-        memory[65] = 0xE0;
-        memory[66] = 0x90;
-        memory[67] = 0x90;
-        memory[68] = 0x90;
-        memory[69] = 0xE0;
-        //E
-        memory[70] = 0xF0;
-        memory[71] = 0x80;
-        memory[72] = 0xF0;
-        memory[73] = 0x80;
-        memory[74] = 0xF0;
-        //F
-        memory[75] = 0xF0;
-        memory[76] = 0x80;
-        memory[77] = 0xF0;
-        memory[78] = 0x80;
-        memory[79] = 0x80;
-
-        memory
+        Ok(())
     }
 }
 
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+mod differential;
 #[cfg(test)]
 mod tests;
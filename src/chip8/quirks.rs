@@ -0,0 +1,76 @@
+//Behaviour that differs between the interpreters CHIP-8 programs were written for
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quirks {
+    //8XY6 and 8XYE shift VY and store the result in VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    //FX55 and FX65 leave I pointing behind the last register they stored or loaded
+    pub load_store_increments_i: bool,
+    //BNNN jumps to XNN plus VX instead of NNN plus V0
+    pub jump_uses_vx: bool,
+    //8XY1, 8XY2 and 8XY3 reset VF to 0
+    pub logic_resets_vf: bool,
+    //Sprites get cut off at the screen edges instead of wrapping around
+    pub clip_sprites: bool,
+    //DXYN waits for the next frame, so at most 60 sprites get drawn per second
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    pub const PRESETS: [(&'static str, Quirks); 4] = [
+        ("default", Quirks::DEFAULT),
+        ("chip8", Quirks::CHIP8),
+        ("schip", Quirks::SCHIP),
+        ("xochip", Quirks::XOCHIP),
+    ];
+
+    //What this emulator always did before quirks were configurable
+    pub const DEFAULT: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    //The original COSMAC VIP interpreter
+    pub const CHIP8: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: true,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    pub const SCHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    pub const XOCHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    pub fn from_name(name: &str) -> Option<Quirks> {
+        Quirks::PRESETS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, quirks)| *quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::DEFAULT
+    }
+}
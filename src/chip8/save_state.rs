@@ -0,0 +1,57 @@
+use super::lockstep::{Core, MachineState};
+use super::{Chip8, Machine, Symbols};
+
+//A running ROM frozen in time, to continue it later on the same ROM or on a rebuilt one
+#[derive(Clone)]
+pub struct SaveState {
+    state: MachineState,
+    machine: Machine,
+    rom_hash: String,
+    rom_len: usize,
+    symbols: Symbols,
+    font_address: u16,
+    cycles: u64,
+}
+
+impl Chip8 {
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            state: self.state(),
+            machine: self.machine.clone(),
+            rom_hash: self.rom_hash.clone(),
+            rom_len: self.rom_len,
+            symbols: self.symbols.clone(),
+            font_address: self.font_address,
+            cycles: self.cycles,
+        }
+    }
+
+    //A rebuilt ROM only takes the state over if its layout is the same: same machine, same size
+    //and its labels where they were. It keeps its own bytes, the RAM around them is restored.
+    pub fn restore_state(&mut self, save: &SaveState) -> bool {
+        let same_rom = save.rom_hash == self.rom_hash;
+        let same_layout = save.rom_len == self.rom_len && save.symbols.same_layout(&self.symbols);
+        if save.machine != self.machine || !(same_rom || same_layout) {
+            return false;
+        }
+
+        let start = self.machine.load_address as usize;
+        let rom = start..start + self.rom_len;
+        let code = self.memory[rom.clone()].to_vec();
+        self.memory = save.state.memory;
+        if !same_rom {
+            self.memory[rom].copy_from_slice(&code);
+        }
+        self.pc = save.state.pc;
+        self.i_reg = save.state.i_reg;
+        self.v = save.state.v;
+        self.stack = save.state.stack.clone();
+        self.sp = self.stack.len();
+        self.delay_timer = save.state.delay_timer;
+        self.sound_timer = save.state.sound_timer;
+        self.gfx = save.state.gfx;
+        self.font_address = save.font_address;
+        self.cycles = save.cycles;
+        true
+    }
+}
@@ -0,0 +1,78 @@
+use super::Chip8;
+
+//How a byte has to relate to the last snapshot to stay a candidate
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Comparison {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u8),
+}
+
+impl Comparison {
+    //A comparison with the snapshot or a hex value like 0x05 or $05
+    pub fn from_name(name: &str) -> Option<Comparison> {
+        let comparison = match name {
+            "equal" => Comparison::Equal,
+            "changed" => Comparison::Changed,
+            "increased" => Comparison::Increased,
+            "decreased" => Comparison::Decreased,
+            _ => {
+                let digits = name.strip_prefix("0x").or_else(|| name.strip_prefix('$'))?;
+                Comparison::Value(u8::from_str_radix(digits, 16).ok()?)
+            }
+        };
+        Some(comparison)
+    }
+
+    fn matches(self, before: u8, now: u8) -> bool {
+        match self {
+            Comparison::Equal => now == before,
+            Comparison::Changed => now != before,
+            Comparison::Increased => now > before,
+            Comparison::Decreased => now < before,
+            Comparison::Value(value) => now == value,
+        }
+    }
+}
+
+//Finds the address of a value like the lives or the score by comparing the memory of the chip
+//between frames. It starts with all RAM the program may write.
+pub struct MemorySearch {
+    candidates: Vec<u16>,
+    snapshot: Vec<u8>,
+}
+
+impl MemorySearch {
+    pub fn start(chip: &Chip8) -> MemorySearch {
+        let ram_size = chip.machine.ram_size.min(chip.memory.len());
+        let candidates = (0..ram_size)
+            .filter(|&address| !chip.machine.is_reserved(&(address..address + 1)))
+            .map(|address| address as u16)
+            .collect();
+        MemorySearch {
+            candidates,
+            snapshot: chip.memory[..ram_size].to_vec(),
+        }
+    }
+
+    //Keeps the candidates that compare to the last snapshot, then takes a new one
+    pub fn narrow(&mut self, chip: &Chip8, comparison: Comparison) -> usize {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&address| {
+            let address = address as usize;
+            comparison.matches(snapshot[address], chip.memory[address])
+        });
+        let ram_size = self.snapshot.len();
+        self.snapshot.copy_from_slice(&chip.memory[..ram_size]);
+        self.candidates.len()
+    }
+
+    //Candidates with their value in the last snapshot
+    pub fn candidates(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.candidates
+            .iter()
+            .map(move |&address| (address, self.snapshot[address as usize]))
+    }
+}
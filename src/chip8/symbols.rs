@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+
+//Label names of a ROM as written by Octo and other assemblers
+#[derive(Clone, Default, Debug)]
+pub struct Symbols {
+    //The first label defined at an address names it
+    labels: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+}
+
+impl Symbols {
+    //One label per line with its address before or after it, like "0200 main", "main = 0x200"
+    //or "main: $200". Comments start with ; or #.
+    pub fn parse(text: &str) -> Result<Symbols, String> {
+        let mut symbols = Symbols::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split([';', '#']).next().unwrap_or("");
+            let fields: Vec<&str> = line
+                .split(|c: char| c.is_whitespace() || c == '=' || c == ':' || c == ',')
+                .filter(|field| !field.is_empty())
+                .collect();
+            let (label, address) = match fields[..] {
+                [] => continue,
+                //Labels like "add" are valid hex, an address marked as hex wins and then the first
+                [first, second] => match (parse_address(first), parse_address(second)) {
+                    (Some((address, true)), _) => (second, address),
+                    (_, Some((address, true))) => (first, address),
+                    (Some((address, false)), _) => (second, address),
+                    (None, Some((address, false))) => (first, address),
+                    (None, None) => return Err(format!("line {}: no address", number + 1)),
+                },
+                _ => {
+                    return Err(format!(
+                        "line {}: expected a label and an address",
+                        number + 1
+                    ))
+                }
+            };
+            symbols.insert(label, address);
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, label: &str, address: u16) {
+        self.labels
+            .entry(address)
+            .or_insert_with(|| label.to_string());
+        self.addresses.insert(label.to_string(), address);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    pub fn address_of(&self, label: &str) -> Option<u16> {
+        self.addresses.get(label).copied()
+    }
+
+    //The closest label at or before the address, like "draw" or "draw+0x6"
+    pub fn resolve(&self, address: u16) -> Option<String> {
+        let (&start, label) = self.labels.range(..=address).next_back()?;
+        Some(match address - start {
+            0 => label.clone(),
+            offset => format!("{}+0x{:X}", label, offset),
+        })
+    }
+
+    //resolve with the plain address as fallback
+    pub fn describe(&self, address: u16) -> String {
+        self.resolve(address)
+            .unwrap_or_else(|| format!("{:03X}", address))
+    }
+
+    //Labels both know are at the same addresses
+    pub fn same_layout(&self, other: &Symbols) -> bool {
+        self.addresses.iter().all(|(label, &address)| {
+            other
+                .address_of(label)
+                .is_none_or(|other_address| other_address == address)
+        })
+    }
+
+    //Breakpoint locations: a hex address, a label or a label with a hex offset like "draw+6"
+    pub fn locate(&self, location: &str) -> Option<u16> {
+        let mut parts = location.trim().splitn(2, '+');
+        let base = parts.next()?;
+        let offset = match parts.next() {
+            Some(offset) => parse_address(offset)?.0,
+            None => 0,
+        };
+        let base = self
+            .address_of(base)
+            .or_else(|| parse_address(base).map(|(address, _)| address))?;
+        base.checked_add(offset)
+    }
+}
+
+//Hex with 0x or $ in front or plain hex, the flag tells whether it was marked as hex
+fn parse_address(text: &str) -> Option<(u16, bool)> {
+    let marked = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'));
+    let digits = marked.unwrap_or(text);
+    let address = u16::from_str_radix(digits, 16).ok()?;
+    Some((address, marked.is_some()))
+}
@@ -1,12 +1,18 @@
 mod tests {
-    use super::super::Chip8;
+    use super::super::{
+        disassemble, disassemble_with, pattern, Cheat, Cheats, Chip8, Chip8Error, Comparison,
+        Coverage, Font, Machine, MemorySearch, Quirks, Scheduler, SpriteDraw, Symbols, Timing,
+        TraceEntry, Tracer,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     #[test]
     fn test_jump() {
         let mut test_chip = Chip8::new();
         test_chip.memory[0x200] = 0x18;
         test_chip.memory[0x201] = 0x54;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x0854);
     }
 
@@ -15,7 +21,7 @@ mod tests {
         let mut test_chip = Chip8::new();
         test_chip.memory[0x200] = 0x28;
         test_chip.memory[0x201] = 0x54;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.pc, 0x0854);
         assert_eq!(test_chip.stack[0], 0x0200);
@@ -30,8 +36,8 @@ mod tests {
         test_chip.memory[0x854] = 0x00;
         test_chip.memory[0x855] = 0xEE;
 
-        test_chip.emulate_cycle();
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.pc, 0x202);
         assert_eq!(test_chip.stack[0], 0x0200);
@@ -45,7 +51,7 @@ mod tests {
         test_chip.memory[0x201] = 0x54;
         test_chip.v[8] = 0x54;
 
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x204);
 
         let mut test_chip2 = Chip8::new();
@@ -53,7 +59,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x54;
         test_chip2.v[8] = 0x64;
 
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
         assert_eq!(test_chip2.pc, 0x202);
     }
 
@@ -64,7 +70,7 @@ mod tests {
         test_chip.memory[0x201] = 0x54;
         test_chip.v[8] = 0x54;
 
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x202);
 
         let mut test_chip2 = Chip8::new();
@@ -72,7 +78,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x54;
         test_chip2.v[8] = 0x64;
 
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
         assert_eq!(test_chip2.pc, 0x204);
     }
 
@@ -84,7 +90,7 @@ mod tests {
         test_chip.v[8] = 0x54;
         test_chip.v[5] = 0x54;
 
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x204);
 
         let mut test_chip2 = Chip8::new();
@@ -93,7 +99,7 @@ mod tests {
         test_chip2.v[8] = 0x64;
         test_chip2.v[5] = 0x54;
 
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
         assert_eq!(test_chip2.pc, 0x202);
     }
 
@@ -102,7 +108,7 @@ mod tests {
         let mut test_chip = Chip8::new();
         test_chip.memory[0x200] = 0x61;
         test_chip.memory[0x201] = 0x05;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 5);
     }
@@ -113,7 +119,7 @@ mod tests {
         test_chip.memory[0x200] = 0x71;
         test_chip.memory[0x201] = 0x05;
         test_chip.v[1] = 5;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 10);
     }
@@ -125,7 +131,7 @@ mod tests {
         test_chip.memory[0x201] = 0x21;
         test_chip.v[1] = 0b0001;
         test_chip.v[2] = 0b1100;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 0b1101);
     }
@@ -137,7 +143,7 @@ mod tests {
         test_chip.memory[0x201] = 0x22;
         test_chip.v[1] = 0b0011;
         test_chip.v[2] = 0b1010;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 0b0010);
     }
@@ -149,7 +155,7 @@ mod tests {
         test_chip.memory[0x201] = 0x23;
         test_chip.v[1] = 0b0011;
         test_chip.v[2] = 0b1010;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 0b1001);
     }
@@ -161,7 +167,7 @@ mod tests {
         test_chip.memory[0x201] = 0x24;
         test_chip.v[1] = 5;
         test_chip.v[2] = 6;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 11);
         assert_eq!(test_chip.v[15], 0);
@@ -171,7 +177,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x24;
         test_chip2.v[1] = u8::max_value();
         test_chip2.v[2] = 6;
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
 
         assert_eq!(test_chip2.v[1], 5);
         assert_eq!(test_chip2.v[15], 1);
@@ -184,7 +190,7 @@ mod tests {
         test_chip.memory[0x201] = 0x25;
         test_chip.v[1] = 7;
         test_chip.v[2] = 2;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 5);
         assert_eq!(test_chip.v[15], 1);
@@ -194,7 +200,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x25;
         test_chip2.v[1] = 5;
         test_chip2.v[2] = 7;
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
 
         assert_eq!(test_chip2.v[1], 254);
         assert_eq!(test_chip2.v[15], 0);
@@ -206,7 +212,7 @@ mod tests {
         test_chip.memory[0x200] = 0x81;
         test_chip.memory[0x201] = 0x26;
         test_chip.v[1] = 0b0110;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 3);
         assert_eq!(test_chip.v[15], 0);
@@ -215,7 +221,7 @@ mod tests {
         test_chip2.memory[0x200] = 0x81;
         test_chip2.memory[0x201] = 0x26;
         test_chip2.v[1] = 0b1111;
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
 
         assert_eq!(test_chip2.v[1], 7);
         assert_eq!(test_chip2.v[15], 1);
@@ -228,7 +234,7 @@ mod tests {
         test_chip.memory[0x201] = 0x27;
         test_chip.v[1] = 2;
         test_chip.v[2] = 7;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 5);
         assert_eq!(test_chip.v[15], 1);
@@ -238,7 +244,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x27;
         test_chip2.v[1] = 7;
         test_chip2.v[2] = 5;
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
 
         assert_eq!(test_chip2.v[1], 254);
         assert_eq!(test_chip2.v[15], 0);
@@ -250,7 +256,7 @@ mod tests {
         test_chip.memory[0x200] = 0x81;
         test_chip.memory[0x201] = 0x2E;
         test_chip.v[1] = 0b0010;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[1], 4);
         assert_eq!(test_chip.v[15], 0);
@@ -259,7 +265,7 @@ mod tests {
         test_chip2.memory[0x200] = 0x81;
         test_chip2.memory[0x201] = 0x2E;
         test_chip2.v[1] = 0b10000010;
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
 
         assert_eq!(test_chip2.v[1], 4);
         assert_eq!(test_chip2.v[15], 1);
@@ -273,7 +279,7 @@ mod tests {
         test_chip.v[8] = 0x54;
         test_chip.v[5] = 0x64;
 
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x204);
 
         let mut test_chip2 = Chip8::new();
@@ -282,7 +288,7 @@ mod tests {
         test_chip2.v[8] = 0x64;
         test_chip2.v[5] = 0x64;
 
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
         assert_eq!(test_chip2.pc, 0x202);
     }
 
@@ -291,7 +297,7 @@ mod tests {
         let mut test_chip = Chip8::new();
         test_chip.memory[0x200] = 0xA5;
         test_chip.memory[0x201] = 0x53;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.i_reg, 0x553);
     }
@@ -302,7 +308,7 @@ mod tests {
         test_chip.memory[0x200] = 0xB5;
         test_chip.memory[0x201] = 0x53;
         test_chip.v[0] = 0x30;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.pc, 0x583);
     }
 
@@ -312,7 +318,7 @@ mod tests {
         test_chip.memory[0x200] = 0xF0;
         test_chip.memory[0x201] = 0x07;
         test_chip.delay_timer = 5;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.v[0], 5);
     }
 
@@ -322,7 +328,7 @@ mod tests {
         test_chip.memory[0x200] = 0xF5;
         test_chip.memory[0x201] = 0x15;
         test_chip.v[5] = 12;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.delay_timer, 11);
     }
 
@@ -332,7 +338,7 @@ mod tests {
         test_chip.memory[0x200] = 0xF5;
         test_chip.memory[0x201] = 0x18;
         test_chip.v[5] = 12;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.sound_timer, 11);
     }
 
@@ -343,7 +349,7 @@ mod tests {
         test_chip.memory[0x201] = 0x1E;
         test_chip.v[5] = 12;
         test_chip.i_reg = 12;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.i_reg, 24);
         assert_eq!(test_chip.v[15], 0);
 
@@ -352,7 +358,7 @@ mod tests {
         test_chip2.memory[0x201] = 0x1E;
         test_chip2.v[5] = 12;
         test_chip2.i_reg = u16::max_value();
-        test_chip2.emulate_cycle();
+        test_chip2.emulate_cycle().unwrap();
         assert_eq!(test_chip2.i_reg, 11);
         assert_eq!(test_chip2.v[15], 1);
     }
@@ -363,17 +369,67 @@ mod tests {
         test_chip.memory[0x200] = 0xF5;
         test_chip.memory[0x201] = 0x29;
         test_chip.v[5] = 5;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.i_reg, 25);
     }
 
+    #[test]
+    fn test_ireg_to_large_sprite_location() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0xF5;
+        test_chip.memory[0x201] = 0x30;
+        test_chip.v[5] = 0x13;
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.i_reg, 80 + 30);
+    }
+
+    #[test]
+    fn test_load_font() {
+        let mut test_chip = Chip8::new();
+        assert_eq!(test_chip.memory[..80], Font::SCHIP.small[..]);
+        test_chip.load_font(&Font::VIP, 0x50).unwrap();
+        assert_eq!(test_chip.font_address(), 0x50);
+        assert_eq!(test_chip.memory[..80], [0; 80][..]);
+        assert_eq!(test_chip.memory[0x50..0xA0], Font::VIP.small[..]);
+        assert_eq!(test_chip.memory[0xA0..0x104], Font::VIP.large[..]);
+
+        test_chip.memory[0x200] = 0xF1;
+        test_chip.memory[0x201] = 0x29;
+        test_chip.v[1] = 0xA;
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.i_reg, 0x50 + 50);
+
+        assert_eq!(
+            test_chip.load_font(&Font::VIP, 0xF80),
+            Err(Chip8Error::AddressOutOfRange(0xF80))
+        );
+        assert_eq!(Font::from_name("dream6800"), Some(Font::DREAM6800));
+        assert_eq!(
+            Font::from_bytes(&Font::ETI660.to_bytes()),
+            Some(Font::ETI660)
+        );
+        assert_eq!(Font::from_bytes(&Font::VIP.small), Some(Font::VIP));
+        assert_eq!(Font::from_bytes(&[0; 81]), None);
+    }
+
+    #[test]
+    fn test_load_font_keeps_program() {
+        let mut test_chip = Chip8::new();
+        test_chip.load_font(&Font::VIP, 0x1C0).unwrap();
+        test_chip.load_into_memory(vec![0xAA; 0x40]).unwrap();
+        test_chip.load_font(&Font::VIP, 0x50).unwrap();
+        assert_eq!(test_chip.memory[0x1C0..0x200], [0; 0x40][..]);
+        assert_eq!(test_chip.memory[0x200..0x240], [0xAA; 0x40][..]);
+        assert_eq!(test_chip.memory[0x240..0x274], [0; 0x34][..]);
+    }
+
     #[test]
     fn test_store_bcd_in_memory() {
         let mut test_chip = Chip8::new();
         test_chip.memory[0x200] = 0xF5;
         test_chip.memory[0x201] = 0x33;
         test_chip.v[5] = 234;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
         assert_eq!(test_chip.memory[test_chip.i_reg as usize], 2);
         assert_eq!(test_chip.memory[test_chip.i_reg as usize + 1], 3);
         assert_eq!(test_chip.memory[test_chip.i_reg as usize + 2], 4);
@@ -389,7 +445,7 @@ mod tests {
         test_chip.v[0] = 234;
         test_chip.v[1] = 2;
         test_chip.v[2] = 35;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.memory[test_chip.i_reg as usize], 234);
         assert_eq!(test_chip.memory[(test_chip.i_reg + 1) as usize], 2);
@@ -406,10 +462,731 @@ mod tests {
         test_chip.memory[0x400] = 234;
         test_chip.memory[0x401] = 2;
         test_chip.memory[0x402] = 35;
-        test_chip.emulate_cycle();
+        test_chip.emulate_cycle().unwrap();
 
         assert_eq!(test_chip.v[0], 234);
         assert_eq!(test_chip.v[1], 2);
         assert_eq!(test_chip.v[2], 35);
     }
+
+    #[test]
+    fn test_shift_quirk() {
+        let mut test_chip = Chip8::new();
+        test_chip.quirks = Quirks::CHIP8;
+        test_chip.memory[0x200] = 0x81;
+        test_chip.memory[0x201] = 0x26;
+        test_chip.v[1] = 0b0110;
+        test_chip.v[2] = 0b1111;
+        test_chip.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip.v[1], 7);
+        assert_eq!(test_chip.v[15], 1);
+
+        let mut test_chip2 = Chip8::new();
+        test_chip2.quirks = Quirks::CHIP8;
+        test_chip2.memory[0x200] = 0x81;
+        test_chip2.memory[0x201] = 0x2E;
+        test_chip2.v[1] = 0b0001;
+        test_chip2.v[2] = 0b1000_0010;
+        test_chip2.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip2.v[1], 4);
+        assert_eq!(test_chip2.v[15], 1);
+    }
+
+    #[test]
+    fn test_shift_into_vf() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0x8F;
+        test_chip.memory[0x201] = 0xFE;
+        test_chip.v[15] = 0b1000_0001;
+        test_chip.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip.v[15], 0b10);
+
+        let mut test_chip2 = Chip8::new();
+        test_chip2.quirks = Quirks::CHIP8;
+        test_chip2.memory[0x200] = 0x8F;
+        test_chip2.memory[0x201] = 0xFE;
+        test_chip2.v[15] = 0b1000_0001;
+        test_chip2.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip2.v[15], 1);
+    }
+
+    #[test]
+    fn test_load_store_quirk() {
+        let mut test_chip = Chip8::new();
+        test_chip.quirks = Quirks::CHIP8;
+        test_chip.memory[0x200] = 0xF2;
+        test_chip.memory[0x201] = 0x55;
+        test_chip.memory[0x202] = 0xF1;
+        test_chip.memory[0x203] = 0x65;
+        test_chip.i_reg = 0x500;
+        test_chip.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip.i_reg, 0x503);
+
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.i_reg, 0x505);
+    }
+
+    #[test]
+    fn test_jump_quirk() {
+        let mut test_chip = Chip8::new();
+        test_chip.quirks = Quirks::SCHIP;
+        test_chip.memory[0x200] = 0xB5;
+        test_chip.memory[0x201] = 0x53;
+        test_chip.v[0] = 0x30;
+        test_chip.v[5] = 0x10;
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.pc, 0x563);
+    }
+
+    #[test]
+    fn test_logic_vf_reset_quirk() {
+        let mut test_chip = Chip8::new();
+        test_chip.quirks = Quirks::CHIP8;
+        test_chip.memory[0x200] = 0x81;
+        test_chip.memory[0x201] = 0x21;
+        test_chip.v[1] = 0b0001;
+        test_chip.v[2] = 0b1100;
+        test_chip.v[15] = 1;
+        test_chip.emulate_cycle().unwrap();
+
+        assert_eq!(test_chip.v[1], 0b1101);
+        assert_eq!(test_chip.v[15], 0);
+    }
+
+    #[test]
+    fn test_display_sprite_wraps() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0xD0;
+        test_chip.memory[0x201] = 0x12;
+        test_chip.memory[0x300] = 0xFF;
+        test_chip.memory[0x301] = 0xFF;
+        test_chip.i_reg = 0x300;
+        test_chip.v[0] = 60;
+        test_chip.v[1] = 31;
+        test_chip.emulate_cycle().unwrap();
+
+        assert!(test_chip.gfx[31][63]);
+        assert!(test_chip.gfx[31][0]);
+        assert!(test_chip.gfx[0][3]);
+    }
+
+    #[test]
+    fn test_display_sprite_clip_quirk() {
+        let mut test_chip = Chip8::new();
+        test_chip.quirks = Quirks::CHIP8;
+        test_chip.memory[0x200] = 0xD0;
+        test_chip.memory[0x201] = 0x12;
+        test_chip.memory[0x300] = 0xFF;
+        test_chip.memory[0x301] = 0xFF;
+        test_chip.i_reg = 0x300;
+        test_chip.v[0] = 60;
+        test_chip.v[1] = 31;
+        //The VIP also waits for the display
+        test_chip.emulate_cycle().unwrap();
+        test_chip.vblank();
+        test_chip.emulate_cycle().unwrap();
+
+        assert!(test_chip.gfx[31][63]);
+        assert!(!test_chip.gfx[31][0]);
+        assert!(!test_chip.gfx[0][3]);
+    }
+
+    #[test]
+    fn test_hires() {
+        //HIGH, draw a 16x16 sprite at 112,0, LOW
+        let mut test_chip = Chip8::from_rom(vec![0x00, 0xFF, 0xD0, 0x10, 0x00, 0xFE]).unwrap();
+        test_chip.i_reg = 0x300;
+        test_chip.memory[0x300] = 0x80;
+        test_chip.memory[0x301] = 0x01;
+        test_chip.memory[0x31E] = 0x80;
+        test_chip.v[0] = 112;
+        test_chip.emulate_cycle().unwrap();
+        assert!(test_chip.gfx.is_hires());
+        assert_eq!((test_chip.gfx.width(), test_chip.gfx.height()), (128, 64));
+
+        test_chip.emulate_cycle().unwrap();
+        assert!(test_chip.gfx[0][112]);
+        assert!(test_chip.gfx[0][127]);
+        assert!(test_chip.gfx[15][112]);
+        assert_eq!(
+            test_chip
+                .gfx
+                .rows()
+                .filter(|row| row.contains(&true))
+                .count(),
+            2
+        );
+        assert_eq!(test_chip.last_sprite.unwrap().height, 16);
+
+        test_chip.emulate_cycle().unwrap();
+        assert!(!test_chip.gfx.is_hires());
+        assert_eq!(test_chip.gfx.rows().count(), 32);
+        assert!(test_chip
+            .gfx
+            .rows()
+            .all(|row| row.len() == 64 && !row.contains(&true)));
+    }
+
+    #[test]
+    fn test_display_wait_quirk() {
+        let mut test_chip = Chip8::from_rom(vec![0xD0, 0x11, 0x12, 0x00]).unwrap();
+        test_chip.quirks.display_wait = true;
+        test_chip.i_reg = 0x300;
+        test_chip.memory[0x300] = 0x80;
+        let coverage = Arc::new(Mutex::new(Coverage::new()));
+        test_chip.coverage = Some(coverage.clone());
+
+        //A frame that began before DXYN doesn't count
+        test_chip.vblank();
+        for _ in 0..3 {
+            test_chip.emulate_cycle().unwrap();
+            assert_eq!(test_chip.pc, 0x200);
+            assert!(!test_chip.gfx[0][0]);
+        }
+        //Stalling doesn't count as executing
+        assert_eq!(test_chip.cycles, 0);
+        assert_eq!(coverage.lock().unwrap().executed[0x200], 0);
+        test_chip.vblank();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.pc, 0x202);
+        assert!(test_chip.gfx[0][0]);
+        assert_eq!(test_chip.cycles, 1);
+        assert_eq!(coverage.lock().unwrap().executed[0x200], 1);
+
+        //The next DXYN waits for the next frame again
+        test_chip.emulate_cycle().unwrap();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.pc, 0x200);
+        assert!(test_chip.gfx[0][0]);
+    }
+
+    #[test]
+    fn test_display_wait_draws_once_per_frame() {
+        let rom = vec![0xD0, 0x11, 0x70, 0x01, 0x12, 0x00];
+        let mut test_chip = Chip8::from_rom(rom.clone()).unwrap();
+        test_chip.quirks = Quirks::CHIP8;
+        let mut scheduler = Scheduler::new(Duration::from_micros(50), 100);
+        //10 frames and the draw and add after the last one
+        for _ in 0..1002 {
+            scheduler.step(&mut test_chip).unwrap();
+        }
+        assert_eq!(test_chip.v[0], 10);
+
+        //With VIP timing the wait is part of the frame, not on top of it
+        let mut test_chip = Chip8::from_rom(rom).unwrap();
+        test_chip.quirks = Quirks::CHIP8;
+        test_chip.timing = Timing::Vip;
+        let mut scheduler = Scheduler::new(Duration::from_micros(50), 100);
+        let mut frames = 0;
+        while frames < 10 {
+            if scheduler.step(&mut test_chip).unwrap().frame_ended {
+                frames += 1;
+            }
+        }
+        assert_eq!(test_chip.v[0], 9);
+    }
+
+    #[test]
+    fn test_hash_rom() {
+        assert_eq!(
+            Chip8::hash_rom(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(disassemble(0x00E0), "CLS");
+        assert_eq!(disassemble(0x2854), "CALL 0x854");
+        assert_eq!(disassemble(0x8AB4), "ADD VA, VB");
+        assert_eq!(disassemble(0xD125), "DRW V1, V2, 5");
+        assert_eq!(disassemble(0xF365), "LD V3, [I]");
+        assert_eq!(disassemble(0x8AB9), "DW 0x8AB9");
+        assert_eq!(disassemble(0x00FF), "HIGH");
+    }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = Symbols::parse(
+            "; from the assembler\n0200 main\ndraw = 0x20A\nadd: $300\n\nbeef 0x400",
+        )
+        .unwrap();
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols.address_of("main"), Some(0x200));
+        assert_eq!(symbols.address_of("draw"), Some(0x20A));
+        assert_eq!(symbols.address_of("add"), Some(0x300));
+        assert_eq!(symbols.address_of("beef"), Some(0x400));
+        assert_eq!(symbols.resolve(0x1FE), None);
+        assert_eq!(symbols.resolve(0x20A).unwrap(), "draw");
+        assert_eq!(symbols.resolve(0x210).unwrap(), "draw+0x6");
+        assert_eq!(symbols.describe(0x100), "100");
+        assert_eq!(symbols.locate("draw+6"), Some(0x210));
+        assert_eq!(symbols.locate("2FE"), Some(0x2FE));
+        assert_eq!(symbols.locate("nowhere"), None);
+
+        assert!(Symbols::parse("main").is_err());
+        assert!(Symbols::parse("main 0x200 0x300").is_err());
+    }
+
+    #[test]
+    fn test_disassemble_with_symbols() {
+        let symbols = Symbols::parse("0x204 draw").unwrap();
+        assert_eq!(disassemble_with(0x2204, &symbols), "CALL draw");
+        assert_eq!(disassemble_with(0x1208, &symbols), "JP draw+0x4");
+        assert_eq!(disassemble_with(0x1200, &symbols), "JP 0x200");
+        assert_eq!(disassemble_with(0x6204, &symbols), "LD V2, 0x04");
+    }
+
+    #[test]
+    fn test_call_stack_labels() {
+        let mut test_chip = Chip8::from_rom(vec![0x22, 0x04, 0x00, 0x00, 0x00, 0xEE]).unwrap();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.call_stack_labels(), ["202"]);
+        test_chip.symbols = Symbols::parse("0x200 main").unwrap();
+        assert_eq!(test_chip.call_stack_labels(), ["main+0x2"]);
+    }
+
+    #[test]
+    fn test_pattern() {
+        assert_eq!(pattern(0x00EE), "00EE");
+        assert_eq!(pattern(0x8AB6), "8XY6");
+        assert_eq!(pattern(0xF20A), "FX0A");
+        assert_eq!(pattern(0xE1A2), "????");
+    }
+
+    struct CollectingTracer(Arc<Mutex<Vec<TraceEntry>>>);
+
+    impl Tracer for CollectingTracer {
+        fn trace(&mut self, entry: &TraceEntry) {
+            self.0.lock().unwrap().push(*entry);
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let mut test_chip = Chip8::new();
+        test_chip.tracer = Some(Box::new(CollectingTracer(entries.clone())));
+        test_chip.memory[0x200] = 0x61;
+        test_chip.memory[0x201] = 0x42;
+        test_chip.memory[0x202] = 0xA3;
+        test_chip.memory[0x203] = 0x00;
+        test_chip.emulate_cycle().unwrap();
+        test_chip.emulate_cycle().unwrap();
+
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].cycle, 1);
+        assert_eq!(entries[0].pc, 0x200);
+        assert_eq!(entries[0].opcode, 0x6142);
+        assert_eq!(entries[0].v[1], 0x42);
+        assert_eq!(entries[1].pc, 0x202);
+        assert_eq!(entries[1].i_reg, 0x300);
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0x22;
+        test_chip.memory[0x201] = 0x00;
+        for _ in 0..16 {
+            test_chip.emulate_cycle().unwrap();
+        }
+
+        assert_eq!(test_chip.emulate_cycle(), Err(Chip8Error::StackOverflow));
+        assert_eq!(test_chip.sp, 16);
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0x00;
+        test_chip.memory[0x201] = 0xEE;
+
+        assert_eq!(test_chip.emulate_cycle(), Err(Chip8Error::StackUnderflow));
+        assert_eq!(test_chip.pc, 0x200);
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        let machine = Machine::from_name("vip").unwrap();
+        let mut test_chip = Chip8::with_machine(machine, vec![0x22, 0x00]).unwrap();
+        for _ in 0..12 {
+            test_chip.emulate_cycle().unwrap();
+        }
+        assert_eq!(test_chip.emulate_cycle(), Err(Chip8Error::StackOverflow));
+
+        let machine = Machine {
+            stack_depth: None,
+            ..Machine::default()
+        };
+        let mut test_chip = Chip8::with_machine(machine, vec![0x22, 0x00]).unwrap();
+        for _ in 0..100 {
+            test_chip.emulate_cycle().unwrap();
+        }
+        assert_eq!(test_chip.sp, 100);
+    }
+
+    #[test]
+    fn test_call_stack() {
+        let mut test_chip = Chip8::from_rom(vec![
+            0x22, 0x04, 0x00, 0x00, 0x22, 0x08, 0x00, 0x00, 0x00, 0xEE,
+        ])
+        .unwrap();
+        assert!(test_chip.call_stack().is_empty());
+        test_chip.emulate_cycle().unwrap();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.call_stack(), [0x202, 0x206]);
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.call_stack(), [0x202]);
+    }
+
+    #[test]
+    fn test_last_write() {
+        let mut test_chip = Chip8::from_rom(vec![0xA3, 0x00, 0xF2, 0x55, 0xF0, 0x33]).unwrap();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.last_write, None);
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.last_write, Some(0x300..0x303));
+        test_chip.i_reg = 0x310;
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(test_chip.last_write, Some(0x310..0x313));
+    }
+
+    #[test]
+    fn test_cheats() {
+        let cheats = Cheats::parse("; lives\n2F0:05 lives\n\n2f1:01:00\n").unwrap();
+        assert_eq!(cheats.len(), 2);
+        assert_eq!(cheats.to_text(), "2F0:05 lives\n2F1:01:00\n");
+        assert!(Cheats::parse("2F0").is_err());
+        assert!(Cheats::parse("2F0:105").is_err());
+        assert!(Cheats::parse("2F0:05:00:00").is_err());
+
+        let mut test_chip = Chip8::new();
+        test_chip.cheats = cheats;
+        test_chip.cheats.add(Cheat::parse("FFFF:01").unwrap());
+        test_chip.memory[0x2F1] = 0x02;
+        test_chip.apply_cheats();
+        assert_eq!(test_chip.memory[0x2F0], 0x05);
+        assert_eq!(test_chip.memory[0x2F1], 0x02);
+        test_chip.memory[0x2F1] = 0x00;
+        test_chip.apply_cheats();
+        assert_eq!(test_chip.memory[0x2F1], 0x01);
+    }
+
+    #[test]
+    fn test_memory_search() {
+        let machine = Machine::from_name("vip").unwrap();
+        let mut test_chip = Chip8::with_machine(machine, vec![0x00, 0xE0]).unwrap();
+        test_chip.memory[0x300] = 3;
+        test_chip.memory[0x301] = 3;
+        let mut search = MemorySearch::start(&test_chip);
+        assert_eq!(search.candidates().count(), 0xEA0 - 0x200);
+
+        test_chip.memory[0x300] = 2;
+        test_chip.memory[0x301] = 4;
+        test_chip.memory[0x302] = 1;
+        assert_eq!(search.narrow(&test_chip, Comparison::Changed), 3);
+        assert_eq!(search.narrow(&test_chip, Comparison::Equal), 3);
+        test_chip.memory[0x300] = 1;
+        assert_eq!(search.narrow(&test_chip, Comparison::Decreased), 1);
+        assert_eq!(search.candidates().collect::<Vec<_>>(), [(0x300, 1)]);
+
+        let mut search = MemorySearch::start(&test_chip);
+        assert_eq!(search.narrow(&test_chip, Comparison::Value(4)), 1);
+        assert_eq!(Comparison::from_name("0x04"), Some(Comparison::Value(4)));
+        assert_eq!(
+            Comparison::from_name("increased"),
+            Some(Comparison::Increased)
+        );
+        assert_eq!(Comparison::from_name("4"), None);
+    }
+
+    #[test]
+    fn test_instruction_timing() {
+        let mut test_chip = Chip8::from_rom(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+        let mut scheduler = Scheduler::new(Duration::from_micros(50), 3);
+        let frames: Vec<bool> = (0..6)
+            .map(|_| scheduler.step(&mut test_chip).unwrap())
+            .map(|step| {
+                assert_eq!(step.duration, Duration::from_micros(50));
+                step.frame_ended
+            })
+            .collect();
+        assert_eq!(frames, [false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_vip_timing() {
+        //Sets the delay timer to 5 and counts up V0 in a loop
+        let mut test_chip =
+            Chip8::from_rom(vec![0x61, 0x05, 0xF1, 0x15, 0x70, 0x01, 0x12, 0x04]).unwrap();
+        test_chip.timing = Timing::Vip;
+        let mut scheduler = Scheduler::new(Duration::from_micros(50), 3);
+        let mut instructions = 0;
+        while !scheduler.step(&mut test_chip).unwrap().frame_ended {
+            instructions += 1;
+        }
+        //The timer only counts down in the interrupt, not with every instruction
+        assert_eq!(test_chip.registers().delay_timer, 4);
+        assert!(instructions > 50 && instructions < 80);
+
+        let mut instructions = 0;
+        let mut duration = Duration::from_secs(0);
+        loop {
+            let step = scheduler.step(&mut test_chip).unwrap();
+            instructions += 1;
+            duration += step.duration;
+            if step.frame_ended {
+                break;
+            }
+        }
+        assert_eq!(test_chip.registers().delay_timer, 3);
+        assert!(instructions > 25 && instructions < 45);
+        assert!(
+            duration > Duration::from_micros(16_000) && duration < Duration::from_micros(17_000)
+        );
+    }
+
+    #[test]
+    fn test_vip_timing_waits_for_display() {
+        let mut test_chip = Chip8::from_rom(vec![0xD0, 0x15, 0x12, 0x00]).unwrap();
+        test_chip.timing = Timing::Vip;
+        let mut scheduler = Scheduler::new(Duration::from_micros(50), 3);
+        for _ in 0..3 {
+            assert!(scheduler.step(&mut test_chip).unwrap().frame_ended);
+            assert!(!scheduler.step(&mut test_chip).unwrap().frame_ended);
+        }
+        //A sprite off the byte boundary takes longer to draw
+        let aligned = scheduler.step(&mut test_chip).unwrap().duration;
+        scheduler.step(&mut test_chip).unwrap();
+        test_chip.v[0] = 4;
+        let shifted = scheduler.step(&mut test_chip).unwrap().duration;
+        assert!(shifted > aligned);
+    }
+
+    #[test]
+    fn test_memory_out_of_range() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0xD0;
+        test_chip.memory[0x201] = 0x1F;
+        test_chip.i_reg = 0xFFF;
+        test_chip.v[15] = 1;
+
+        assert_eq!(
+            test_chip.emulate_cycle(),
+            Err(Chip8Error::AddressOutOfRange(0xFFF))
+        );
+        assert_eq!(test_chip.v[15], 1);
+
+        test_chip.pc = 0xFFF;
+        assert_eq!(
+            test_chip.emulate_cycle(),
+            Err(Chip8Error::AddressOutOfRange(0xFFF))
+        );
+    }
+
+    #[test]
+    fn test_rom_too_large() {
+        assert!(Chip8::from_rom(vec![0; 4096 - 0x200]).is_ok());
+        assert_eq!(
+            Chip8::from_rom(vec![0; 4096 - 0x1FF]).err(),
+            Some(Chip8Error::RomTooLarge(4096 - 0x1FF))
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let coverage = Arc::new(Mutex::new(Coverage::new()));
+        let mut test_chip = Chip8::new();
+        test_chip.coverage = Some(coverage.clone());
+        test_chip.memory[0x200] = 0xD0;
+        test_chip.memory[0x201] = 0x03;
+        test_chip.memory[0x202] = 0xF1;
+        test_chip.memory[0x203] = 0x55;
+        test_chip.i_reg = 0x300;
+        test_chip.emulate_cycle().unwrap();
+        test_chip.emulate_cycle().unwrap();
+
+        let coverage = coverage.lock().unwrap();
+        assert_eq!(coverage.executed[0x200..0x205], [1, 1, 1, 1, 0]);
+        assert_eq!(coverage.read[0x2FF..0x304], [0, 1, 1, 1, 0]);
+        assert_eq!(coverage.written[0x2FF..0x303], [0, 1, 1, 0]);
+
+        let mut merged = Coverage::from_bytes(&coverage.to_bytes()).unwrap();
+        merged.merge(&coverage);
+        assert_eq!(merged.executed[0x200], 2);
+        assert_eq!(merged.written[0x301], 2);
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut test_chip = Chip8::new();
+        let mut registers = test_chip.registers();
+        registers.v[3] = 0x42;
+        registers.i_reg = 0x300;
+        registers.pc = 0x204;
+        registers.sp = 2;
+        test_chip.set_registers(&registers).unwrap();
+        assert_eq!(test_chip.registers(), registers);
+        assert_eq!(test_chip.v[3], 0x42);
+
+        registers.sp = 17;
+        assert_eq!(
+            test_chip.set_registers(&registers),
+            Err(Chip8Error::StackOverflow)
+        );
+        assert_eq!(test_chip.sp, 2);
+    }
+
+    #[test]
+    fn test_registers_deep_stack() {
+        let machine = Machine {
+            stack_depth: None,
+            ..Machine::default()
+        };
+        let mut test_chip = Chip8::with_machine(machine, vec![0x22, 0x00]).unwrap();
+        for _ in 0..300 {
+            test_chip.emulate_cycle().unwrap();
+        }
+        let mut registers = test_chip.registers();
+        assert_eq!(registers.sp, 255);
+
+        registers.pc = 0x202;
+        test_chip.set_registers(&registers).unwrap();
+        assert_eq!(test_chip.sp, 300);
+        assert_eq!(test_chip.pc, 0x202);
+
+        registers.sp = 10;
+        test_chip.set_registers(&registers).unwrap();
+        assert_eq!(test_chip.sp, 10);
+    }
+
+    #[test]
+    fn test_debug_memory() {
+        let mut test_chip = Chip8::new();
+        test_chip.write_memory(0x300, &[1, 2, 3]).unwrap();
+        assert_eq!(test_chip.read_memory(0x2FF, 5).unwrap(), [0, 1, 2, 3, 0]);
+        assert_eq!(
+            test_chip.write_memory(0xFFF, &[1, 2]),
+            Err(Chip8Error::AddressOutOfRange(0xFFF))
+        );
+        assert!(test_chip.read_memory(0xFFF, 1).is_ok());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0x12;
+        test_chip.memory[0x201] = 0x08;
+        test_chip.breakpoints.insert(0x208);
+        assert!(!test_chip.at_breakpoint());
+        test_chip.emulate_cycle().unwrap();
+        assert!(test_chip.at_breakpoint());
+    }
+
+    #[test]
+    fn test_last_sprite() {
+        let mut test_chip = Chip8::new();
+        test_chip.memory[0x200] = 0xD0;
+        test_chip.memory[0x201] = 0x15;
+        test_chip.i_reg = 0x0A;
+        test_chip.v[0] = 70;
+        test_chip.v[1] = 3;
+        assert_eq!(test_chip.last_sprite, None);
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(
+            test_chip.last_sprite,
+            Some(SpriteDraw {
+                address: 0x0A,
+                height: 5,
+                x: 6,
+                y: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_machine_load_address() {
+        let machine = Machine::from_name("eti660").unwrap();
+        let test_chip = Chip8::with_machine(machine, vec![0x12, 0x34]).unwrap();
+        assert_eq!(test_chip.pc, 0x600);
+        assert_eq!(test_chip.memory[0x600..0x602], [0x12, 0x34]);
+        assert_eq!(test_chip.memory[0x200], 0);
+        assert_eq!(
+            Chip8::with_machine(Machine::from_name("eti660").unwrap(), vec![0; 0xA01]).err(),
+            Some(Chip8Error::RomTooLarge(0xA01))
+        );
+    }
+
+    #[test]
+    fn test_machine_ram_size() {
+        let machine = Machine::from_name("vip2k").unwrap();
+        assert!(Chip8::with_machine(machine.clone(), vec![0; 0x4A0]).is_ok());
+        assert_eq!(
+            Chip8::with_machine(machine.clone(), vec![0; 0x4A1]).err(),
+            Some(Chip8Error::RomTooLarge(0x4A1))
+        );
+
+        assert_eq!(machine.max_rom_size(), 0x4A0);
+        assert_eq!(Machine::default().max_rom_size(), 4096 - 0x200);
+
+        let mut test_chip = Chip8::with_machine(machine, vec![0x18, 0x00]).unwrap();
+        test_chip.emulate_cycle().unwrap();
+        assert_eq!(
+            test_chip.emulate_cycle(),
+            Err(Chip8Error::AddressOutOfRange(0x800))
+        );
+    }
+
+    #[test]
+    fn test_reserved_write() {
+        let machine = Machine::from_name("vip").unwrap();
+        let mut test_chip = Chip8::with_machine(machine, vec![0xF1, 0x55, 0xF1, 0x65]).unwrap();
+        test_chip.i_reg = 0xE9F;
+        assert_eq!(
+            test_chip.emulate_cycle(),
+            Err(Chip8Error::ReservedWrite(0xE9F))
+        );
+
+        //Reading the interpreter's memory is allowed
+        test_chip.pc = 0x202;
+        test_chip.emulate_cycle().unwrap();
+    }
+
+    #[test]
+    fn test_restore_state() {
+        //Counts V0 up in a loop and keeps the count at 0x300
+        let rom = vec![0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+        let mut test_chip = Chip8::from_rom(rom.clone()).unwrap();
+        for _ in 0..10 {
+            test_chip.emulate_cycle().unwrap();
+        }
+        let save = test_chip.save_state();
+
+        //Counting by two instead, same size so the count carries over
+        let mut rebuilt =
+            Chip8::from_rom(vec![0x70, 0x02, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00]).unwrap();
+        assert!(rebuilt.restore_state(&save));
+        assert_eq!(rebuilt.pc, test_chip.pc);
+        assert_eq!(rebuilt.v[0], 3);
+        assert_eq!(rebuilt.memory[0x300], 2);
+        assert_eq!(rebuilt.memory[0x201], 0x02);
+
+        let mut longer = Chip8::from_rom([rom, vec![0x00, 0xE0]].concat()).unwrap();
+        assert!(!longer.restore_state(&save));
+        assert_eq!(longer.pc, 0x200);
+
+        //A label that moved means the code did, even if the size stayed the same
+        let mut moved =
+            Chip8::from_rom(vec![0x70, 0x01, 0xF0, 0x55, 0xA3, 0x00, 0x12, 0x00]).unwrap();
+        moved.symbols = Symbols::parse("0x202 store").unwrap();
+        test_chip.symbols = Symbols::parse("0x204 store").unwrap();
+        assert!(!moved.restore_state(&test_chip.save_state()));
+    }
 }
@@ -0,0 +1,175 @@
+use super::{Chip8, Chip8Error};
+use std::time::Duration;
+
+//How long instructions take and when the timers count down
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Timing {
+    //Every instruction takes the same time and counts the timers down
+    #[default]
+    Instructions,
+    //Instructions take as many machine cycles as in the interpreter of the COSMAC VIP, the timers
+    //count down in the display interrupt 60 times a second
+    Vip,
+}
+
+impl Timing {
+    pub const NAMES: [&'static str; 2] = ["instructions", "vip"];
+
+    pub fn from_name(name: &str) -> Option<Timing> {
+        match name {
+            "instructions" => Some(Timing::Instructions),
+            "vip" => Some(Timing::Vip),
+            _ => None,
+        }
+    }
+}
+
+//The 1802 of the VIP runs at 1.7609 MHz and needs 8 clocks per machine cycle
+const VIP_CYCLE_TIME: Duration = Duration::from_nanos(4543);
+const VIP_CYCLES_PER_FRAME: u32 = 3668;
+//The interrupt routine keeps the CPU busy while the 1861 shows the 128 lines of the display
+const VIP_INTERRUPT_CYCLES: u32 = 1832;
+//Fetching and decoding an instruction, on top of the cost of executing it
+const VIP_FETCH_CYCLES: u32 = 40;
+
+//One instruction as the frontend has to wait for it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Step {
+    pub duration: Duration,
+    //Once per frame, for everything the frontend does per frame
+    pub frame_ended: bool,
+}
+
+//Runs the chip with the timing it is set to and keeps track of the frames
+pub struct Scheduler {
+    cycle_time: Duration,
+    cycles_per_frame: u32,
+    //Instructions or machine cycles since the frame began
+    cycles: u32,
+}
+
+impl Scheduler {
+    //Instructions per second the frontends run at unless told otherwise
+    pub const DEFAULT_SPEED: u32 = 20_000;
+
+    //cycle_time and cycles_per_frame are for Timing::Instructions
+    pub fn new(cycle_time: Duration, cycles_per_frame: u32) -> Scheduler {
+        Scheduler {
+            cycle_time,
+            cycles_per_frame: cycles_per_frame.max(1),
+            cycles: 0,
+        }
+    }
+
+    //Speed in instructions per second, spread over 60 frames
+    pub fn with_speed(speed: u32) -> Scheduler {
+        let speed = speed.max(1);
+        Scheduler::new(
+            Duration::from_nanos(1_000_000_000 / speed as u64),
+            speed / 60,
+        )
+    }
+
+    pub fn step(&mut self, chip: &mut Chip8) -> Result<Step, Chip8Error> {
+        match chip.timing {
+            Timing::Instructions => {
+                chip.emulate_cycle()?;
+                self.cycles += 1;
+                let frame_ended = self.cycles >= self.cycles_per_frame;
+                if frame_ended {
+                    self.cycles = 0;
+                    chip.vblank();
+                }
+                Ok(Step {
+                    duration: self.cycle_time,
+                    frame_ended,
+                })
+            }
+            Timing::Vip => self.step_vip(chip),
+        }
+    }
+
+    fn step_vip(&mut self, chip: &mut Chip8) -> Result<Step, Chip8Error> {
+        let pc = chip.pc;
+        chip.emulate_cycle()?;
+        let opcode = chip.opcode;
+        let skipped = chip.pc == pc.wrapping_add(4);
+
+        let mut cycles = 0;
+        let mut frame_ended = false;
+        //DXYN waits for the interrupt before it draws. With the display_wait quirk the chip
+        //stalls by itself and draws once the interrupt is over.
+        if opcode & 0xF000 == 0xD000 {
+            let stalled = chip.pc == pc;
+            if stalled || !chip.quirks.display_wait {
+                cycles += VIP_CYCLES_PER_FRAME - self.cycles;
+                self.cycles = VIP_CYCLES_PER_FRAME;
+                frame_ended |= self.end_frames(chip, &mut cycles);
+            }
+            if stalled {
+                return Ok(Step {
+                    duration: VIP_CYCLE_TIME * cycles,
+                    frame_ended,
+                });
+            }
+        }
+        let cost = VIP_FETCH_CYCLES + vip_cycles(chip, opcode, skipped);
+        cycles += cost;
+        self.cycles += cost;
+        frame_ended |= self.end_frames(chip, &mut cycles);
+
+        Ok(Step {
+            duration: VIP_CYCLE_TIME * cycles,
+            frame_ended,
+        })
+    }
+
+    //Runs the interrupts of the frames that began, their cycles add to the instruction
+    fn end_frames(&mut self, chip: &mut Chip8, cycles: &mut u32) -> bool {
+        let mut frame_ended = false;
+        while self.cycles >= VIP_CYCLES_PER_FRAME {
+            self.cycles -= VIP_CYCLES_PER_FRAME;
+            self.cycles += VIP_INTERRUPT_CYCLES;
+            *cycles += VIP_INTERRUPT_CYCLES;
+            chip.tick_timers();
+            chip.vblank();
+            frame_ended = true;
+        }
+        frame_ended
+    }
+}
+
+//Machine cycles the VIP interpreter spends executing an instruction, approximated from the
+//routines in its ROM. Skips that are taken cost 2 more.
+fn vip_cycles(chip: &Chip8, opcode: u16, skipped: bool) -> u32 {
+    let x = ((opcode & 0x0F00) >> 8) as u32;
+    let skip = if skipped { 2 } else { 0 };
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => 24,
+            _ => 23,
+        },
+        0x1000 | 0x2000 | 0xB000 => 23,
+        0x3000 | 0x4000 => 10 + skip,
+        0x5000 | 0x9000 => 14 + skip,
+        0x6000 => 6,
+        0x7000 => 10,
+        0x8000 => 44,
+        0xA000 => 12,
+        0xC000 => 36,
+        //Sprites that don't start at a byte boundary of the display get shifted row by row
+        0xD000 => match chip.last_sprite {
+            Some(sprite) if sprite.x % 8 == 0 => 26 + 16 * sprite.height as u32,
+            Some(sprite) => 26 + 28 * sprite.height as u32,
+            None => 26,
+        },
+        0xE000 => 14 + skip,
+        _ => match opcode & 0x00FF {
+            0x1E => 19,
+            0x29 => 20,
+            0x33 => 204,
+            0x55 | 0x65 => 14 + 14 * (x + 1),
+            _ => 10,
+        },
+    }
+}
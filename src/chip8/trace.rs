@@ -0,0 +1,23 @@
+//Machine state right after an instruction was executed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub v: [u8; 16],
+    pub i_reg: u16,
+}
+
+//Gets called by emulate_cycle for every executed instruction
+pub trait Tracer: Send {
+    fn trace(&mut self, entry: &TraceEntry);
+}
+
+//Lets several tracers watch the same chip
+impl Tracer for Vec<Box<dyn Tracer>> {
+    fn trace(&mut self, entry: &TraceEntry) {
+        for tracer in self.iter_mut() {
+            tracer.trace(entry);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::chip8::{disassemble, Coverage};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+//Coverage collected into a file, every run adds its counts to the ones already in it
+pub struct CoverageOutput {
+    pub path: PathBuf,
+    pub rom: Vec<u8>,
+    load_address: usize,
+    previous: Coverage,
+    //Only this run, filled in by the chip
+    pub coverage: Arc<Mutex<Coverage>>,
+}
+
+impl CoverageOutput {
+    pub fn open(path: &Path, rom: Vec<u8>, load_address: u16) -> io::Result<CoverageOutput> {
+        let previous = match fs::read(path) {
+            Ok(data) => Coverage::from_bytes(&data)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a coverage file"))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Coverage::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(CoverageOutput {
+            path: path.to_path_buf(),
+            rom,
+            load_address: load_address as usize,
+            previous,
+            coverage: Arc::new(Mutex::new(Coverage::new())),
+        })
+    }
+
+    //The merged counts and a text and HTML heatmap next to them
+    pub fn save(&self) -> io::Result<()> {
+        let mut coverage = self.previous.clone();
+        coverage.merge(&self.coverage.lock().unwrap());
+        let report_path = |extension: &str| format!("{}.{}", self.path.display(), extension);
+
+        fs::write(&self.path, coverage.to_bytes())?;
+        let rom = Rom {
+            data: &self.rom,
+            start: self.load_address,
+        };
+        fs::write(report_path("txt"), text_report(&coverage, &rom))?;
+        fs::write(report_path("html"), html_report(&coverage, &rom))
+    }
+}
+
+//The ROM and where it was loaded
+struct Rom<'a> {
+    data: &'a [u8],
+    start: usize,
+}
+
+struct Row {
+    address: usize,
+    bytes: [u8; 2],
+    executed: u32,
+    read: u32,
+    written: u32,
+}
+
+//One row per word from the load address up to the end of the ROM or the last touched byte
+fn rows(coverage: &Coverage, rom: &Rom) -> Vec<Row> {
+    let touched =
+        |a: usize| coverage.executed[a] > 0 || coverage.read[a] > 0 || coverage.written[a] > 0;
+    let last_touched = (rom.start..4096)
+        .rev()
+        .find(|&a| touched(a))
+        .map_or(0, |a| a + 1);
+    let end = (rom.start + rom.data.len()).max(last_touched).min(4096);
+
+    (rom.start..end)
+        .step_by(2)
+        .map(|address| {
+            let byte = |a: usize| rom.data.get(a - rom.start).copied().unwrap_or(0);
+            let range = address..(address + 2).min(4096);
+            Row {
+                address,
+                bytes: [byte(address), byte(address + 1)],
+                executed: range.clone().map(|a| coverage.executed[a]).max().unwrap(),
+                read: range.clone().map(|a| coverage.read[a]).max().unwrap(),
+                written: range.map(|a| coverage.written[a]).max().unwrap(),
+            }
+        })
+        .collect()
+}
+
+fn summary(coverage: &Coverage, rom: &Rom) -> String {
+    let rom_range = rom.start..(rom.start + rom.data.len()).min(4096);
+    let count = |counts: &[u32]| rom_range.clone().filter(|&a| counts[a] > 0).count();
+    let percent = |n: usize| n as f64 * 100.0 / rom.data.len().max(1) as f64;
+    let (executed, read, written) = (
+        count(&coverage.executed),
+        count(&coverage.read),
+        count(&coverage.written),
+    );
+    format!(
+        "{} ROM bytes, {} executed ({:.1}%), {} read as data ({:.1}%), {} written ({:.1}%)",
+        rom.data.len(),
+        executed,
+        percent(executed),
+        read,
+        percent(read),
+        written,
+        percent(written)
+    )
+}
+
+//X executed, R read, W written, the counts are per word
+fn text_report(coverage: &Coverage, rom: &Rom) -> String {
+    let mut report = summary(coverage, rom) + "\n\n";
+    for row in rows(coverage, rom) {
+        let opcode = u16::from_be_bytes(row.bytes);
+        report.push_str(&format!(
+            "{:03X}  {:04X}  {}{}{}  {:>10} {:>10} {:>10}  {}\n",
+            row.address,
+            opcode,
+            if row.executed > 0 { 'X' } else { '-' },
+            if row.read > 0 { 'R' } else { '-' },
+            if row.written > 0 { 'W' } else { '-' },
+            row.executed,
+            row.read,
+            row.written,
+            if row.executed > 0 {
+                disassemble(opcode)
+            } else {
+                String::new()
+            }
+        ));
+    }
+    report
+}
+
+//Green for code, blue for data read and red for writes, brighter the more often
+fn html_report(coverage: &Coverage, rom: &Rom) -> String {
+    let rows = rows(coverage, rom);
+    let max = |f: fn(&Row) -> u32| rows.iter().map(f).max().unwrap_or(0).max(1) as f64;
+    let (max_executed, max_read, max_written) =
+        (max(|r| r.executed), max(|r| r.read), max(|r| r.written));
+    //Logarithmic so a few hot loops don't make everything else look unused
+    let heat = |count: u32, max: f64| {
+        if count == 0 {
+            0
+        } else {
+            64 + (191.0 * (count as f64).ln_1p() / max.ln_1p()) as u32
+        }
+    };
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Coverage</title>\n\
+         <style>body{font-family:monospace;background:#111;color:#ddd}\
+         td{padding:0 8px}</style></head><body>\n",
+    );
+    html.push_str(&format!("<p>{}</p>\n<table>\n", summary(coverage, rom)));
+    html.push_str(
+        "<tr><th>Address</th><th>Opcode</th><th>Executed</th><th>Read</th><th>Written</th>\
+         <th>Instruction</th></tr>\n",
+    );
+    for row in rows.iter() {
+        let opcode = u16::from_be_bytes(row.bytes);
+        html.push_str(&format!(
+            "<tr style=\"background:rgb({},{},{})\"><td>{:03X}</td><td>{:04X}</td>\
+             <td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            heat(row.written, max_written) / 2,
+            heat(row.executed, max_executed) / 2,
+            heat(row.read, max_read) / 2,
+            row.address,
+            opcode,
+            row.executed,
+            row.read,
+            row.written,
+            if row.executed > 0 {
+                disassemble(opcode)
+            } else {
+                String::new()
+            }
+        ));
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
@@ -0,0 +1,125 @@
+use crate::chip8::Display;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub type Gfx = Display;
+//Rows of the current resolution
+pub type Intensities = Vec<Vec<u8>>;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterMode {
+    Off,
+    Decay,
+    Blend,
+}
+
+impl FilterMode {
+    pub fn from_name(name: &str) -> Option<FilterMode> {
+        match name {
+            "off" => Some(FilterMode::Off),
+            "decay" => Some(FilterMode::Decay),
+            "blend" => Some(FilterMode::Blend),
+            _ => None,
+        }
+    }
+
+    fn next(self) -> FilterMode {
+        match self {
+            FilterMode::Off => FilterMode::Decay,
+            FilterMode::Decay => FilterMode::Blend,
+            FilterMode::Blend => FilterMode::Off,
+        }
+    }
+}
+
+//Sits between Chip8.gfx and the texture upload to hide the flicker of XOR drawn sprites
+pub struct DisplayFilter {
+    pub mode: FilterMode,
+    half_life: Duration,
+    blend_frames: usize,
+    phosphor: Vec<Vec<f32>>,
+    history: VecDeque<Gfx>,
+}
+
+impl DisplayFilter {
+    pub fn new(mode: FilterMode, half_life: Duration, blend_frames: usize) -> DisplayFilter {
+        DisplayFilter {
+            mode,
+            half_life,
+            blend_frames: blend_frames.max(1),
+            phosphor: Vec::new(),
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next();
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.phosphor.clear();
+        self.history.clear();
+    }
+
+    pub fn apply(&mut self, gfx: &Gfx, frame_time: Duration) -> Intensities {
+        //Frames of the other resolution don't mix with the new ones
+        if self.phosphor.len() != gfx.height() {
+            self.reset();
+            self.phosphor = vec![vec![0.0; gfx.width()]; gfx.height()];
+        }
+        match self.mode {
+            FilterMode::Off => Self::unfiltered(gfx),
+            FilterMode::Decay => self.decay(gfx, frame_time),
+            FilterMode::Blend => self.blend(gfx),
+        }
+    }
+
+    fn unfiltered(gfx: &Gfx) -> Intensities {
+        gfx.rows()
+            .map(|row| row.iter().map(|&set| if set { 255 } else { 0 }).collect())
+            .collect()
+    }
+
+    //Lit pixels are at full brightness, unlit ones fade out by half every half-life
+    fn decay(&mut self, gfx: &Gfx, frame_time: Duration) -> Intensities {
+        let half_life = self.half_life.as_secs_f32();
+        let falloff = if half_life > 0.0 {
+            0.5_f32.powf(frame_time.as_secs_f32() / half_life)
+        } else {
+            0.0
+        };
+
+        let mut intensities = vec![vec![0; gfx.width()]; gfx.height()];
+        for i in 0..gfx.height() {
+            for j in 0..gfx.width() {
+                let level = if gfx[i][j] {
+                    1.0
+                } else {
+                    self.phosphor[i][j] * falloff
+                };
+                self.phosphor[i][j] = level;
+                intensities[i][j] = (level * 255.0).round() as u8;
+            }
+        }
+        intensities
+    }
+
+    //A pixel is shown if it was set in any of the last blend_frames frames
+    fn blend(&mut self, gfx: &Gfx) -> Intensities {
+        self.history.push_back(*gfx);
+        while self.history.len() > self.blend_frames {
+            self.history.pop_front();
+        }
+
+        let mut combined = *gfx;
+        for frame in self.history.iter() {
+            for y in 0..gfx.height() {
+                for x in 0..gfx.width() {
+                    combined[y][x] |= frame[y][x];
+                }
+            }
+        }
+        Self::unfiltered(&combined)
+    }
+}
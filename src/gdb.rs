@@ -0,0 +1,793 @@
+use crate::chip8::{Cheat, Chip8, Chip8Error, Comparison, MemorySearch, Registers, Scheduler};
+use crate::watch::{self, Reload};
+use crate::{Options, Shared};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+//How many cycles run between looking for a new debugger or an interrupt from the current one
+const POLL_CYCLES: u32 = 256;
+const REGISTER_COUNT: usize = 21;
+//Search results up to this many get listed
+const SEARCH_LIST_LEN: usize = 32;
+//How often a halted chip looks whether the emulator quits
+const QUIT_POLL_TIME: Duration = Duration::from_millis(100);
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+pub fn listen(port: u16) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    println!("Waiting for GDB on port {}", port);
+    Ok(listener)
+}
+
+//Holds the chip until a debugger attaches and serves the GDB remote serial protocol, after the
+//debugger detaches the chip runs like in the normal logic thread. Registers 0-15 are V0-VF, then I, PC, SP, DT and ST.
+pub fn start(
+    listener: TcpListener,
+    chip: Chip8,
+    options: &Options,
+    shared: Shared,
+    reloads: Receiver<Reload>,
+) -> JoinHandle<()> {
+    let mut stub = Stub {
+        chip,
+        scheduler: options.scheduler(),
+        cheat_directory: options.cheat_directory.clone(),
+        shared,
+        reloads,
+        crashed: None,
+        search: None,
+    };
+    thread::spawn(move || stub.run(listener))
+}
+
+struct Stub {
+    chip: Chip8,
+    scheduler: Scheduler,
+    cheat_directory: PathBuf,
+    shared: Shared,
+    reloads: Receiver<Reload>,
+    //The chip stays where it failed so the debugger can look at it
+    crashed: Option<Chip8Error>,
+    search: Option<MemorySearch>,
+}
+
+enum Action {
+    Reply(String),
+    Resume { step: bool },
+    Detach,
+    //Quits the emulator the same way as closing the window
+    Kill,
+}
+
+impl Stub {
+    fn run(&mut self, listener: TcpListener) {
+        let mut attached = false;
+        loop {
+            if self.shared.quit.load(Ordering::Relaxed) {
+                return;
+            }
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    println!("GDB attached");
+                    attached = true;
+                    if let Err(e) = self.serve(stream) {
+                        println!("GDB connection lost: {}", e);
+                    }
+                    println!("GDB detached");
+                    if self.shared.quit.load(Ordering::Relaxed) {
+                        return;
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    println!("GDB stub stopped: {}", e);
+                    return;
+                }
+            }
+
+            self.take_reload();
+            if !attached || self.crashed.is_some() {
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            for _ in 0..POLL_CYCLES {
+                if let Err(e) = self.cycle() {
+                    println!("Emulation stopped: {}", e);
+                    self.crashed = Some(e);
+                    break;
+                }
+            }
+        }
+    }
+
+    //A rebuilt ROM replaces the chip, also one that crashed
+    fn take_reload(&mut self) {
+        if let Ok(reload) = self.reloads.try_recv() {
+            reload.apply(&mut self.chip);
+            self.crashed = None;
+        }
+    }
+
+    //One cycle the same way the normal logic thread runs it
+    fn cycle(&mut self) -> Result<(), Chip8Error> {
+        let before_cycle = Instant::now();
+
+        self.take_reload();
+        self.chip.key_pressed = *self.shared.keys.lock().unwrap();
+        let result = self.scheduler.step(&mut self.chip);
+        if let Ok(step) = result {
+            if step.frame_ended {
+                self.chip.apply_cheats();
+            }
+        }
+        *self.shared.gfx.lock().unwrap() = self.chip.gfx;
+        self.shared
+            .sound
+            .store(self.chip.sound_active(), Ordering::Relaxed);
+        self.shared.machine_state.fulfil(&self.chip);
+
+        if let Ok(step) = result {
+            thread::sleep(
+                step.duration
+                    .checked_sub(before_cycle.elapsed())
+                    .unwrap_or_default(),
+            );
+        }
+        result.map(|_| ())
+    }
+
+    fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(QUIT_POLL_TIME))?;
+        let mut connection = Connection {
+            stream,
+            quit: self.shared.quit.clone(),
+        };
+        let mut last_signal = if self.crashed.is_some() {
+            SIGSEGV
+        } else {
+            SIGTRAP
+        };
+
+        while let Some(packet) = connection.read_packet()? {
+            let action = match packet.as_str() {
+                "?" => Action::Reply(stop_reply(last_signal)),
+                "k" => Action::Kill,
+                _ => self.handle(&packet),
+            };
+            match action {
+                Action::Reply(reply) => connection.send(&reply)?,
+                Action::Resume { step } => {
+                    last_signal = self.resume(&mut connection, step)?;
+                    connection.send(&stop_reply(last_signal))?;
+                }
+                Action::Detach => {
+                    connection.send("OK")?;
+                    return Ok(());
+                }
+                Action::Kill => {
+                    self.shared.quit.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, packet: &str) -> Action {
+        let mut chars = packet.chars();
+        let command = match chars.next() {
+            Some(command) => command,
+            None => return Action::Reply(String::new()),
+        };
+        let arguments = chars.as_str();
+        let reply = match command {
+            'g' => self.read_registers(),
+            'G' => self.write_registers(arguments),
+            'p' => self.read_register(arguments),
+            'P' => self.write_register(arguments),
+            'm' => self.read_memory(arguments),
+            'M' => self.write_memory(arguments),
+            'Z' | 'z' => self.breakpoint(command == 'Z', arguments),
+            'c' | 's' => {
+                if !arguments.is_empty() {
+                    let address = match parse_address(arguments) {
+                        Some(address) => address,
+                        None => return Action::Reply(error()),
+                    };
+                    let mut registers = self.chip.registers();
+                    registers.pc = address;
+                    self.chip.set_registers(&registers).unwrap();
+                }
+                return Action::Resume {
+                    step: command == 's',
+                };
+            }
+            'D' => return Action::Detach,
+            'H' | 'T' => "OK".to_string(),
+            'q' => match arguments.strip_prefix("Rcmd,") {
+                Some(command) => self.monitor(command),
+                None => query(arguments),
+            },
+            _ => String::new(),
+        };
+        Action::Reply(reply)
+    }
+
+    //GDB's monitor command, locations are addresses or labels of the symbol file
+    fn monitor(&mut self, command: &str) -> String {
+        let command = match decode_hex(command).and_then(|bytes| String::from_utf8(bytes).ok()) {
+            Some(command) => command,
+            None => return error(),
+        };
+        let symbols = &self.chip.symbols;
+        let mut words = command.split_whitespace();
+        let output = match (words.next(), words.next()) {
+            (Some("break"), Some(location)) => match symbols.locate(location) {
+                Some(address) => {
+                    self.chip.breakpoints.insert(address);
+                    format!("Breakpoint at {}\n", symbols.describe(address))
+                }
+                None => format!("Unknown location {}\n", location),
+            },
+            (Some("delete"), Some(location)) => match symbols.locate(location) {
+                Some(address) if self.chip.breakpoints.remove(&address) => {
+                    format!("Deleted breakpoint at {}\n", symbols.describe(address))
+                }
+                _ => format!("No breakpoint at {}\n", location),
+            },
+            //Innermost frame first like GDB's backtrace
+            (Some("where"), None) => {
+                let mut frames = vec![symbols.describe(self.chip.registers().pc)];
+                frames.extend(self.chip.call_stack_labels().into_iter().rev());
+                frames
+                    .iter()
+                    .enumerate()
+                    .map(|(n, frame)| format!("#{} {}\n", n, frame))
+                    .collect()
+            }
+            (Some("cheat"), _) => self.cheat(arguments(&command)),
+            (Some("search"), _) => self.search(arguments(&command)),
+            _ => concat!(
+                "Commands: break LOCATION, delete LOCATION, where,\n",
+                "cheat [CODE [NAME] | delete N | save],\n",
+                "search [equal | changed | increased | decreased | 0xVALUE | list]\n"
+            )
+            .to_string(),
+        };
+        encode_hex(output.as_bytes())
+    }
+
+    //Lists, adds, deletes or saves the cheats of the ROM
+    fn cheat(&mut self, arguments: &str) -> String {
+        let cheats = &mut self.chip.cheats;
+        let mut words = arguments.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) if cheats.is_empty() => "No cheats\n".to_string(),
+            (None, _) => cheats
+                .iter()
+                .enumerate()
+                .map(|(n, cheat)| format!("#{} {}\n", n, cheat))
+                .collect(),
+            (Some("delete"), Some(n)) => match n.parse().ok().and_then(|n| cheats.remove(n)) {
+                Some(cheat) => format!("Deleted cheat {}\n", cheat),
+                None => format!("No cheat #{}\n", n),
+            },
+            (Some("save"), None) => {
+                let path = watch::cheat_file(&self.cheat_directory, &self.chip.rom_hash);
+                match fs::create_dir_all(&self.cheat_directory)
+                    .and_then(|()| fs::write(&path, cheats.to_text()))
+                {
+                    Ok(()) => format!("Saved {} cheats to {}\n", cheats.len(), path.display()),
+                    Err(e) => format!("Could not save cheats to {}: {}\n", path.display(), e),
+                }
+            }
+            _ => match Cheat::parse(arguments) {
+                Some(cheat) => {
+                    let reply = format!("Cheat #{} {}\n", cheats.len(), cheat);
+                    cheats.add(cheat);
+                    reply
+                }
+                None => format!("Invalid cheat {}\n", arguments),
+            },
+        }
+    }
+
+    //Without arguments a new search starts with all of RAM, continue and interrupt the ROM
+    //between the comparisons
+    fn search(&mut self, arguments: &str) -> String {
+        let search = match (arguments, self.search.as_mut()) {
+            ("", _) => {
+                let search = MemorySearch::start(&self.chip);
+                let reply = format!("{} candidates\n", search.candidates().count());
+                self.search = Some(search);
+                return reply;
+            }
+            (_, None) => return "No search started, start one with search\n".to_string(),
+            (_, Some(search)) => search,
+        };
+        let count = match arguments {
+            "list" => search.candidates().count(),
+            _ => match Comparison::from_name(arguments) {
+                Some(comparison) => search.narrow(&self.chip, comparison),
+                None => return format!("Unknown comparison {}\n", arguments),
+            },
+        };
+        let mut reply = format!("{} candidates\n", count);
+        if count <= SEARCH_LIST_LEN || arguments == "list" {
+            for (address, value) in search.candidates().take(SEARCH_LIST_LEN) {
+                reply += &format!("{:03X} = {:02X}\n", address, value);
+            }
+        }
+        reply
+    }
+
+    //Runs until a breakpoint, an error or an interrupt from GDB and returns the signal to report
+    fn resume(&mut self, connection: &mut Connection, step: bool) -> io::Result<u8> {
+        if self.crashed.is_some() {
+            return Ok(SIGSEGV);
+        }
+
+        //The instruction at a breakpoint that was just reported runs first
+        let mut cycles = 0;
+        loop {
+            if let Err(e) = self.cycle() {
+                println!("Emulation stopped: {}", e);
+                self.crashed = Some(e);
+                return Ok(SIGSEGV);
+            }
+            if step || self.chip.at_breakpoint() {
+                return Ok(SIGTRAP);
+            }
+
+            cycles += 1;
+            if cycles % POLL_CYCLES == 0
+                && (connection.interrupted()? || self.shared.quit.load(Ordering::Relaxed))
+            {
+                return Ok(SIGINT);
+            }
+        }
+    }
+
+    fn read_registers(&self) -> String {
+        (0..REGISTER_COUNT)
+            .map(|n| encode_register(&self.chip.registers(), n))
+            .collect()
+    }
+
+    fn write_registers(&mut self, data: &str) -> String {
+        let bytes = match decode_hex(data) {
+            Some(bytes) => bytes,
+            None => return error(),
+        };
+        let mut registers = self.chip.registers();
+        let mut offset = 0;
+        for n in 0..REGISTER_COUNT {
+            let size = register_size(n);
+            match bytes.get(offset..offset + size) {
+                Some(value) => decode_register(&mut registers, n, value),
+                None => return error(),
+            }
+            offset += size;
+        }
+        self.set_registers(&registers)
+    }
+
+    fn read_register(&self, arguments: &str) -> String {
+        match parse_hex(arguments) {
+            Some(n) if n < REGISTER_COUNT => encode_register(&self.chip.registers(), n),
+            _ => error(),
+        }
+    }
+
+    fn write_register(&mut self, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, '=');
+        let n = parts.next().and_then(parse_hex);
+        let value = parts.next().and_then(decode_hex);
+        match (n, value) {
+            (Some(n), Some(value)) if n < REGISTER_COUNT && value.len() == register_size(n) => {
+                let mut registers = self.chip.registers();
+                decode_register(&mut registers, n, &value);
+                self.set_registers(&registers)
+            }
+            _ => error(),
+        }
+    }
+
+    fn set_registers(&mut self, registers: &Registers) -> String {
+        match self.chip.set_registers(registers) {
+            Ok(()) => "OK".to_string(),
+            Err(_) => error(),
+        }
+    }
+
+    fn read_memory(&self, arguments: &str) -> String {
+        let (address, len) = match parse_address_length(arguments) {
+            Some(range) => range,
+            None => return error(),
+        };
+        match self.chip.read_memory(address, len) {
+            Ok(bytes) => encode_hex(bytes),
+            Err(_) => error(),
+        }
+    }
+
+    fn write_memory(&mut self, arguments: &str) -> String {
+        let mut parts = arguments.splitn(2, ':');
+        let range = parts.next().and_then(parse_address_length);
+        let data = parts.next().and_then(decode_hex);
+        match (range, data) {
+            (Some((address, len)), Some(data)) if data.len() == len => {
+                match self.chip.write_memory(address, &data) {
+                    Ok(()) => "OK".to_string(),
+                    Err(_) => error(),
+                }
+            }
+            _ => error(),
+        }
+    }
+
+    //Software and hardware breakpoints are the same thing here, watchpoints aren't supported
+    fn breakpoint(&mut self, insert: bool, arguments: &str) -> String {
+        let mut parts = arguments.split(',');
+        let kind = parts.next();
+        let address = parts.next().map(parse_address);
+        match (kind, address) {
+            (Some("0"), Some(Some(address))) | (Some("1"), Some(Some(address))) => {
+                if insert {
+                    self.chip.breakpoints.insert(address);
+                } else {
+                    self.chip.breakpoints.remove(&address);
+                }
+                "OK".to_string()
+            }
+            (Some("0"), _) | (Some("1"), _) => error(),
+            _ => String::new(),
+        }
+    }
+}
+
+//What follows the first word of a monitor command
+fn arguments(command: &str) -> &str {
+    let command = command.trim();
+    command
+        .find(char::is_whitespace)
+        .map_or("", |end| command[end..].trim())
+}
+
+struct Connection {
+    stream: TcpStream,
+    //The connection counts as closed once the emulator quits
+    quit: Arc<AtomicBool>,
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.stream.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    if self.quit.load(Ordering::Relaxed) {
+                        return Ok(None);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    //Acknowledges every packet, None once the debugger closed the connection
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            //Acks and interrupts while halted don't need an answer
+            match self.read_byte()? {
+                Some(b'$') => {}
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                    None => return Ok(None),
+                }
+            }
+            let mut checksum = [0; 2];
+            for digit in checksum.iter_mut() {
+                match self.read_byte()? {
+                    Some(byte) => *digit = byte,
+                    None => return Ok(None),
+                }
+            }
+
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|c| u8::from_str_radix(c, 16).ok());
+            if expected == Some(checksum_of(&data)) {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+
+    //GDB sends a single 0x03 byte to stop a running target
+    fn interrupted(&mut self) -> io::Result<bool> {
+        let mut byte = [0];
+        self.stream.set_nonblocking(true)?;
+        let result = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => Ok(byte[0] == 0x03),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn query(arguments: &str) -> String {
+    const FEATURES: &str = "Xfer:features:read:target.xml:";
+
+    if arguments.starts_with("Supported") {
+        "PacketSize=1000;qXfer:features:read+".to_string()
+    } else if arguments == "Attached" {
+        "1".to_string()
+    } else if let Some(range) = arguments.strip_prefix(FEATURES) {
+        let xml = target_xml();
+        let mut parts = range.split(',');
+        let offset = parts.next().and_then(parse_hex).unwrap_or(0).min(xml.len());
+        let len = parts.next().and_then(parse_hex).unwrap_or(0);
+        let end = (offset + len).min(xml.len());
+        let more = if end < xml.len() { 'm' } else { 'l' };
+        format!("{}{}", more, &xml[offset..end])
+    } else {
+        String::new()
+    }
+}
+
+fn target_xml() -> String {
+    let mut registers: Vec<String> = (0..16)
+        .map(|n| format!("<reg name=\"v{:x}\" bitsize=\"8\" type=\"uint8\"/>", n))
+        .collect();
+    registers.push("<reg name=\"i\" bitsize=\"16\" type=\"data_ptr\"/>".to_string());
+    registers.push("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>".to_string());
+    for name in ["sp", "dt", "st"].iter() {
+        registers.push(format!(
+            "<reg name=\"{}\" bitsize=\"8\" type=\"uint8\"/>",
+            name
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+         <target version=\"1.0\"><feature name=\"org.chip8.core\">{}</feature></target>",
+        registers.concat()
+    )
+}
+
+fn register_size(n: usize) -> usize {
+    match n {
+        16 | 17 => 2,
+        _ => 1,
+    }
+}
+
+//Little endian like the target description says
+fn encode_register(registers: &Registers, n: usize) -> String {
+    match n {
+        0..=15 => encode_hex(&[registers.v[n]]),
+        16 => encode_hex(&registers.i_reg.to_le_bytes()),
+        17 => encode_hex(&registers.pc.to_le_bytes()),
+        18 => encode_hex(&[registers.sp]),
+        19 => encode_hex(&[registers.delay_timer]),
+        _ => encode_hex(&[registers.sound_timer]),
+    }
+}
+
+fn decode_register(registers: &mut Registers, n: usize, value: &[u8]) {
+    match n {
+        0..=15 => registers.v[n] = value[0],
+        16 => registers.i_reg = u16::from_le_bytes([value[0], value[1]]),
+        17 => registers.pc = u16::from_le_bytes([value[0], value[1]]),
+        18 => registers.sp = value[0],
+        19 => registers.delay_timer = value[0],
+        _ => registers.sound_timer = value[0],
+    }
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+//None for addresses the chip can't have
+fn parse_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text, 16).ok()
+}
+
+fn parse_address_length(text: &str) -> Option<(u16, usize)> {
+    let mut parts = text.splitn(2, ',');
+    let address = parts.next().and_then(parse_address)?;
+    let len = parts.next().and_then(parse_hex)?;
+    Some((address, len))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_hex, decode_register, encode_register, query, target_xml, Action, Connection, Stub,
+    };
+    use crate::chip8::Chip8;
+    use crate::Shared;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    fn stub() -> Stub {
+        Stub {
+            chip: Chip8::from_rom(vec![0x12, 0x00]).unwrap(),
+            scheduler: crate::chip8::Scheduler::with_speed(600),
+            cheat_directory: PathBuf::new(),
+            shared: Shared::default(),
+            reloads: mpsc::channel().1,
+            crashed: None,
+            search: None,
+        }
+    }
+
+    fn reply(stub: &mut Stub, packet: &str) -> String {
+        match stub.handle(packet) {
+            Action::Reply(reply) => reply,
+            _ => panic!("{} didn't get a reply", packet),
+        }
+    }
+
+    //The stub's end of a connection and the debugger's end
+    fn connect() -> (Connection, TcpStream) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let debugger = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let connection = Connection {
+            stream,
+            quit: Shared::default().quit,
+        };
+        (connection, debugger)
+    }
+
+    #[test]
+    fn test_packets() {
+        let (mut connection, mut debugger) = connect();
+        debugger.write_all(b"+$g#00$g#67").unwrap();
+        assert_eq!(connection.read_packet().unwrap().as_deref(), Some("g"));
+        let mut acks = [0; 2];
+        debugger.read_exact(&mut acks).unwrap();
+        assert_eq!(&acks, b"-+");
+
+        connection.send("OK").unwrap();
+        let mut packet = [0; 6];
+        debugger.read_exact(&mut packet).unwrap();
+        assert_eq!(&packet, b"$OK#9a");
+
+        drop(debugger);
+        assert_eq!(connection.read_packet().unwrap(), None);
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("0aFf"), Some(vec![0x0A, 0xFF]));
+        assert_eq!(decode_hex(""), Some(Vec::new()));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut registers = stub().chip.registers();
+        registers.v[2] = 0x42;
+        registers.i_reg = 0x1234;
+        assert_eq!(encode_register(&registers, 2), "42");
+        assert_eq!(encode_register(&registers, 16), "3412");
+        assert_eq!(encode_register(&registers, 17), "0002");
+
+        decode_register(&mut registers, 17, &[0x04, 0x03]);
+        assert_eq!(registers.pc, 0x304);
+
+        let mut stub = stub();
+        assert_eq!(reply(&mut stub, "g").len(), (16 + 2 + 2 + 3) * 2);
+        assert_eq!(reply(&mut stub, "P11=0403"), "OK");
+        assert_eq!(reply(&mut stub, "p11"), "0403");
+        assert_eq!(reply(&mut stub, "p15"), "E01");
+    }
+
+    #[test]
+    fn test_target_xml_pages() {
+        let xml = target_xml();
+        let mut read = String::new();
+        loop {
+            let page = query(&format!(
+                "Xfer:features:read:target.xml:{:x},40",
+                read.len()
+            ));
+            read.push_str(&page[1..]);
+            if page.starts_with('l') {
+                break;
+            }
+            assert!(page.starts_with('m'));
+            assert_eq!(page.len(), 0x41);
+        }
+        assert_eq!(read, xml);
+        assert_eq!(
+            query(&format!("Xfer:features:read:target.xml:{:x},10", xml.len())),
+            "l"
+        );
+    }
+
+    #[test]
+    fn test_addresses_out_of_range() {
+        let mut stub = stub();
+        assert_eq!(reply(&mut stub, "c10000"), "E01");
+        assert_eq!(reply(&mut stub, "s1x"), "E01");
+        assert_eq!(reply(&mut stub, "Z0,10200,2"), "E01");
+        assert!(stub.chip.breakpoints.is_empty());
+
+        assert_eq!(reply(&mut stub, "Z0,204,2"), "OK");
+        assert!(stub.chip.breakpoints.contains(&0x204));
+        assert_eq!(reply(&mut stub, "z0,204,2"), "OK");
+        assert!(stub.chip.breakpoints.is_empty());
+        assert!(matches!(
+            stub.handle("c204"),
+            Action::Resume { step: false }
+        ));
+        assert_eq!(stub.chip.registers().pc, 0x204);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::chip8::{Chip8, Lockstep};
+use crate::recorder::Recording;
+use crate::script::Script;
+use crate::{save_reports, take_screenshot, Options};
+use std::process;
+
+//Runs as fast as possible without a window or keyboard, for recordings and automated runs
+pub fn emulate(mut chip: Chip8, frames: u64, options: &Options, mut script: Option<Script>) {
+    let mut recording = Recording::start(options);
+    if let Some(script) = &mut script {
+        script.start(&mut chip);
+    }
+
+    let mut scheduler = options.scheduler();
+
+    'running: for frame in 0..frames {
+        loop {
+            //Without a keyboard only the script presses keys
+            if let Some(script) = &script {
+                chip.key_pressed = [false; 16];
+                script.press_keys(&mut chip.key_pressed);
+            }
+            let step = match scheduler.step(&mut chip) {
+                Ok(step) => step,
+                Err(e) => {
+                    println!("Emulation stopped: {}", e);
+                    break 'running;
+                }
+            };
+            if let Some(script) = &mut script {
+                script.after_cycle(&mut chip);
+            }
+            if step.frame_ended {
+                break;
+            }
+        }
+
+        if options.screenshot_at_frame == Some(frame) {
+            eprintln!("{}", take_screenshot(options, frame, &chip.gfx, 1));
+        }
+        recording.add_frame(&chip.gfx, chip.sound_active());
+
+        chip.apply_cheats();
+        if let Some(script) = &mut script {
+            script.end_frame(&mut chip);
+            if script.quit_requested() {
+                break;
+            }
+        }
+    }
+
+    recording.finish();
+    save_reports(options);
+
+    //Lets a script fail a test run
+    if let Some(failures) = script.map(|script| script.failures()).filter(|&n| n > 0) {
+        println!("{} script checks failed", failures);
+        process::exit(1);
+    }
+}
+
+//Runs a second chip next to the first and stops at the first instruction where they differ
+pub fn diff(chip: Chip8, other: Chip8, frames: u64, options: &Options) {
+    let cycles = options.cycles_per_frame() as u64;
+    let mut lockstep = Lockstep::new(chip, other);
+    for _ in 0..frames {
+        if let Err(divergence) = lockstep.run(cycles, &[false; 16]) {
+            println!("{}", divergence);
+            return;
+        }
+        lockstep.left.vblank();
+        lockstep.right.vblank();
+    }
+    println!("No divergence in {} cycles", frames * cycles);
+}
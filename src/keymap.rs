@@ -0,0 +1,44 @@
+//The keys for the hex keypad 0 to F, laid out as the 4x4 block below the number row
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Keymap {
+    keys: [char; 16],
+}
+
+impl Keymap {
+    pub const PRESETS: [(&'static str, &'static str); 3] = [
+        ("qwertz", "x123qweasdyc4rfv"),
+        ("qwerty", "x123qweasdzc4rfv"),
+        ("azerty", "x123azeqsdwc4rfv"),
+    ];
+
+    //Either the name of a preset or 16 keys in the order 0 to F
+    pub fn from_name(name: &str) -> Option<Keymap> {
+        let layout = Keymap::PRESETS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or(name, |(_, layout)| *layout);
+
+        let chars: Vec<char> = layout.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if chars.len() != 16 {
+            return None;
+        }
+        let mut keys = [' '; 16];
+        keys.copy_from_slice(&chars);
+        Some(Keymap { keys })
+    }
+
+    pub fn key_index(&self, key: char) -> Option<usize> {
+        let key = key.to_ascii_lowercase();
+        self.keys.iter().position(|&k| k == key)
+    }
+
+    pub fn keys(&self) -> &[char; 16] {
+        &self.keys
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::from_name(Keymap::PRESETS[0].0).unwrap()
+    }
+}
@@ -0,0 +1,315 @@
+use crate::chip8::{Machine, Quirks};
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+use crate::settings::Settings;
+use iui::controls::{
+    Button, Combobox, Control, Entry, Group, HorizontalBox, Label, Spinbox, VerticalBox,
+};
+use iui::prelude::*;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+
+struct LauncherState {
+    settings: Settings,
+    roms: Vec<PathBuf>,
+    selected: Option<usize>,
+    start: Option<PathBuf>,
+    max_rom_size: u64,
+}
+
+//Shows the ROMs of a directory and the settings, returns the ROM to start once the user picks one.
+//ROMs are checked against the memory of the machine chosen on the command line.
+pub fn run(settings: Settings, machine: &Machine) -> Option<(PathBuf, Settings)> {
+    let ui = UI::init().unwrap();
+    let mut win = Window::new(&ui, "Chip-8 Emulator", 480, 420, WindowType::NoMenubar);
+    let state = Rc::new(RefCell::new(LauncherState {
+        settings,
+        roms: Vec::new(),
+        selected: None,
+        start: None,
+        max_rom_size: machine.max_rom_size() as u64,
+    }));
+
+    let mut vbox = VerticalBox::new(&ui);
+    vbox.set_padded(&ui, true);
+
+    //ROM directory and the ROMs in it
+    let mut directory_entry = Entry::new(&ui);
+    directory_entry.set_value(
+        &ui,
+        &state.borrow().settings.rom_directory.display().to_string(),
+    );
+    let mut rom_group = Group::new(&ui, "ROMs");
+    let metadata_label = Label::new(&ui, "");
+
+    let mut scan_button = Button::new(&ui, "Scan");
+    scan_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let state = state.clone();
+        let directory_entry = directory_entry.clone();
+        let mut rom_group = rom_group.clone();
+        let metadata_label = metadata_label.clone();
+        move |_| {
+            state.borrow_mut().settings.rom_directory = PathBuf::from(directory_entry.value(&ui));
+            fill_rom_list(&ui, &mut rom_group, &state, &metadata_label, None);
+        }
+    });
+
+    let mut browse_button = Button::new(&ui, "Browse...");
+    browse_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let state = state.clone();
+        let win = win.clone();
+        let mut directory_entry = directory_entry.clone();
+        let mut rom_group = rom_group.clone();
+        let metadata_label = metadata_label.clone();
+        move |_| {
+            if let Some(rom) = win.open_file(&ui) {
+                let directory = rom.parent().map_or(PathBuf::from("."), Path::to_path_buf);
+                directory_entry.set_value(&ui, &directory.display().to_string());
+                state.borrow_mut().settings.rom_directory = directory;
+                fill_rom_list(&ui, &mut rom_group, &state, &metadata_label, Some(&rom));
+            }
+        }
+    });
+
+    let mut directory_box = HorizontalBox::new(&ui);
+    directory_box.set_padded(&ui, true);
+    directory_box.append(&ui, directory_entry, LayoutStrategy::Stretchy);
+    directory_box.append(&ui, scan_button, LayoutStrategy::Compact);
+    directory_box.append(&ui, browse_button, LayoutStrategy::Compact);
+
+    fill_rom_list(&ui, &mut rom_group, &state, &metadata_label, None);
+
+    //Emulation settings
+    let settings = state.borrow().settings.clone();
+    let quirk_names: Vec<&str> = Quirks::PRESETS.iter().map(|(name, _)| *name).collect();
+    let palette_names: Vec<&str> = Palette::PRESETS.iter().map(|(name, _)| *name).collect();
+    let keymap_names: Vec<&str> = Keymap::PRESETS.iter().map(|(name, _)| *name).collect();
+
+    let quirks_box = choice(&ui, &quirk_names, &settings.quirks, {
+        let state = state.clone();
+        move |name| state.borrow_mut().settings.quirks = name
+    });
+    let palette_box = choice(&ui, &palette_names, &settings.palette, {
+        let state = state.clone();
+        move |name| state.borrow_mut().settings.palette = name
+    });
+    let keymap_box = choice(&ui, &keymap_names, &settings.keymap, {
+        let state = state.clone();
+        move |name| state.borrow_mut().settings.keymap = name
+    });
+
+    let mut speed_spinbox = Spinbox::new(&ui, 60, 1_000_000);
+    speed_spinbox.set_value(&ui, settings.speed as i64);
+    speed_spinbox.on_changed(&ui, {
+        let state = state.clone();
+        move |value| state.borrow_mut().settings.speed = value as u32
+    });
+    let mut scale_spinbox = Spinbox::new(&ui, 1, 40);
+    scale_spinbox.set_value(&ui, settings.scale as i64);
+    scale_spinbox.on_changed(&ui, {
+        let state = state.clone();
+        move |value| state.borrow_mut().settings.scale = value as u32
+    });
+
+    let mut settings_box = VerticalBox::new(&ui);
+    settings_box.set_padded(&ui, true);
+    settings_box.append(
+        &ui,
+        labeled(&ui, "Quirks", quirks_box),
+        LayoutStrategy::Compact,
+    );
+    settings_box.append(
+        &ui,
+        labeled(&ui, "Instructions per second", speed_spinbox),
+        LayoutStrategy::Compact,
+    );
+    settings_box.append(
+        &ui,
+        labeled(&ui, "Scale", scale_spinbox),
+        LayoutStrategy::Compact,
+    );
+    settings_box.append(
+        &ui,
+        labeled(&ui, "Palette", palette_box),
+        LayoutStrategy::Compact,
+    );
+    settings_box.append(
+        &ui,
+        labeled(&ui, "Keymap", keymap_box),
+        LayoutStrategy::Compact,
+    );
+    let mut settings_group = Group::new(&ui, "Settings");
+    settings_group.set_child(&ui, settings_box);
+
+    //Start and quit
+    let mut start_button = Button::new(&ui, "Start");
+    start_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        let state = state.clone();
+        let win = win.clone();
+        move |_| {
+            let selected = state.borrow().selected;
+            match selected {
+                Some(index) => {
+                    let mut state = state.borrow_mut();
+                    state.start = Some(state.roms[index].clone());
+                    ui.quit();
+                }
+                None => win.modal_msg(&ui, "No ROM selected", "Pick a ROM to start first."),
+            }
+        }
+    });
+
+    let mut quit_button = Button::new(&ui, "Quit");
+    quit_button.on_clicked(&ui, {
+        let ui = ui.clone();
+        move |_| ui.quit()
+    });
+
+    let mut button_box = HorizontalBox::new(&ui);
+    button_box.set_padded(&ui, true);
+    button_box.append(&ui, start_button, LayoutStrategy::Stretchy);
+    button_box.append(&ui, quit_button, LayoutStrategy::Compact);
+
+    vbox.append(&ui, directory_box, LayoutStrategy::Compact);
+    vbox.append(&ui, rom_group, LayoutStrategy::Compact);
+    vbox.append(&ui, metadata_label, LayoutStrategy::Stretchy);
+    vbox.append(&ui, settings_group, LayoutStrategy::Compact);
+    vbox.append(&ui, button_box, LayoutStrategy::Compact);
+
+    win.set_child(&ui, vbox);
+
+    win.show(&ui);
+    ui.main();
+
+    unsafe {
+        win.destroy();
+    }
+
+    let state = state.borrow();
+    state.start.clone().map(|rom| (rom, state.settings.clone()))
+}
+
+//The list can't be emptied in libui, so every scan puts a new one into the group
+fn fill_rom_list(
+    ui: &UI,
+    rom_group: &mut Group,
+    state: &Rc<RefCell<LauncherState>>,
+    metadata_label: &Label,
+    select: Option<&Path>,
+) {
+    let roms = find_roms(&state.borrow().settings.rom_directory);
+    let mut rom_list = Combobox::new(ui);
+    for rom in roms.iter() {
+        rom_list.append(ui, &rom.file_name().unwrap().to_string_lossy());
+    }
+
+    rom_list.on_selected(ui, {
+        let ui = ui.clone();
+        let state = state.clone();
+        let mut metadata_label = metadata_label.clone();
+        move |index| {
+            let mut state = state.borrow_mut();
+            state.selected = if index >= 0 {
+                Some(index as usize)
+            } else {
+                None
+            };
+            let text = state.selected.map_or(String::new(), |i| {
+                rom_metadata(&state.roms[i], state.max_rom_size)
+            });
+            metadata_label.set_text(&ui, &text);
+        }
+    });
+
+    let mut state = state.borrow_mut();
+    state.selected = select.and_then(|rom| roms.iter().position(|r| r == rom));
+    let mut metadata_label = metadata_label.clone();
+    match state.selected {
+        Some(index) => {
+            rom_list.set_selected(ui, index as i64);
+            metadata_label.set_text(ui, &rom_metadata(&roms[index], state.max_rom_size));
+        }
+        None => metadata_label.set_text(ui, &format!("{} ROMs found", roms.len())),
+    }
+    state.roms = roms;
+
+    rom_group.set_child(ui, rom_list);
+}
+
+fn choice<F: FnMut(String) + 'static>(
+    ui: &UI,
+    names: &[&str],
+    current: &str,
+    mut on_selected: F,
+) -> Combobox {
+    let mut combobox = Combobox::new(ui);
+    for name in names {
+        combobox.append(ui, name);
+    }
+    if let Some(index) = names.iter().position(|name| *name == current) {
+        combobox.set_selected(ui, index as i64);
+    }
+
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    combobox.on_selected(ui, move |index| {
+        if let Some(name) = names.get(index as usize) {
+            on_selected(name.clone());
+        }
+    });
+    combobox
+}
+
+fn labeled<T: Into<Control>>(ui: &UI, text: &str, control: T) -> HorizontalBox {
+    let mut hbox = HorizontalBox::new(ui);
+    hbox.set_padded(ui, true);
+    hbox.append(ui, Label::new(ui, text), LayoutStrategy::Stretchy);
+    hbox.append(ui, control, LayoutStrategy::Compact);
+    hbox
+}
+
+fn find_roms(directory: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .is_some_and(|e| ROM_EXTENSIONS.contains(&e.as_str()))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    roms.sort();
+    roms
+}
+
+//Size, whether it fits into memory and the description many ROM packs ship as a .txt next to it
+fn rom_metadata(rom: &Path, max_rom_size: u64) -> String {
+    let size = fs::metadata(rom).map(|m| m.len()).unwrap_or(0);
+    let mut text = format!(
+        "{}\n{} bytes{}",
+        rom.display(),
+        size,
+        if size > max_rom_size {
+            ", too big for memory"
+        } else {
+            ""
+        }
+    );
+
+    if let Ok(description) = fs::read_to_string(rom.with_extension("txt")) {
+        text.push('\n');
+        for line in description.lines().take(8) {
+            text.push('\n');
+            text.push_str(line);
+        }
+    }
+    text
+}
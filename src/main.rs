@@ -1,27 +1,116 @@
 extern crate clap;
+extern crate crossterm;
+extern crate gif;
+extern crate hound;
 extern crate iui;
+extern crate png;
 extern crate rand;
 extern crate sdl2;
+extern crate serde_json;
+extern crate sha1;
 
 mod chip8;
+mod coverage;
+mod filter;
+mod gdb;
+mod headless;
+mod keymap;
+mod launcher;
+mod overlay;
+mod palette;
+mod profiler;
+mod recorder;
+mod rom_database;
+mod screenshot;
+mod script;
+mod settings;
+mod sprite_viewer;
+mod text;
+mod trace;
+mod tty;
+mod watch;
 
-use crate::chip8::Chip8;
-use clap::{App, Arg};
-use iui::controls::{Button, Group, Label, VerticalBox};
-use iui::prelude::*;
+use crate::chip8::{Chip8, Font, Machine, Quirks, Scheduler, Timing, Tracer};
+use crate::coverage::CoverageOutput;
+use crate::filter::{DisplayFilter, FilterMode, Gfx, Intensities};
+use crate::keymap::Keymap;
+use crate::overlay::{Overlay, StateMailbox, View};
+use crate::palette::Palette;
+use crate::profiler::{Profile, Profiler};
+use crate::recorder::Recording;
+use crate::rom_database::{RomConfig, RomDatabase, RomInfo};
+use crate::script::Script;
+use crate::settings::Settings;
+use crate::trace::{TraceFormat, TraceWriter};
+use crate::watch::{ChipSetup, Reload, RomWatcher};
+use clap::{App, Arg, ArgMatches};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::PixelFormatEnum;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
 use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::video::{FullscreenType, Window};
 use sdl2::EventPump;
 use std::collections::HashSet;
-use std::fs::File;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
-const PITCH: usize = Chip8::SCREEN_WIDTH * 4;
+const FRAME_TIME_NS: u128 = 16_666_666; //60Fps
+
+pub struct Options {
+    pub rom_name: String,
+    pub palette: Palette,
+    pub keymap: Keymap,
+    pub speed: u32,
+    pub fullscreen: bool,
+    pub screenshot_at_frame: Option<u64>,
+    pub screenshot_native: bool,
+    pub record: Option<PathBuf>,
+    pub dump_frames: Option<PathBuf>,
+    pub profile: Option<Arc<Mutex<Profile>>>,
+    pub coverage: Option<CoverageOutput>,
+    pub gdb_port: Option<u16>,
+    pub cheat_directory: PathBuf,
+}
+
+//Everything the thread that runs the chip shares with the frontend
+#[derive(Clone)]
+pub struct Shared {
+    pub keys: Arc<Mutex<[bool; 16]>>,
+    pub gfx: Arc<Mutex<Gfx>>,
+    pub sound: Arc<AtomicBool>,
+    pub machine_state: StateMailbox,
+    //Set when a script asks to quit
+    pub quit: Arc<AtomicBool>,
+}
+
+impl Default for Shared {
+    fn default() -> Shared {
+        Shared {
+            keys: Arc::new(Mutex::new([false; 16])),
+            gfx: Arc::new(Mutex::new(Gfx::default())),
+            sound: Arc::new(AtomicBool::new(false)),
+            machine_state: StateMailbox::default(),
+            quit: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Options {
+    pub fn cycles_per_frame(&self) -> u32 {
+        (self.speed / 60).max(1)
+    }
+
+    pub fn scheduler(&self) -> Scheduler {
+        Scheduler::with_speed(self.speed)
+    }
+}
 
 fn main() {
     let matches = App::new("Chip-8 Emulator")
@@ -31,148 +120,978 @@ fn main() {
                 .short("f")
                 .long("file")
                 .takes_value(true)
-                .help("File to be emulated"),
+                .help("File to be emulated, without it the launcher opens"),
+        )
+        .arg(
+            Arg::with_name("frontend")
+                .long("frontend")
+                .takes_value(true)
+                .possible_values(&["sdl", "tty", "headless"])
+                .help("Render in an SDL window, in the terminal or not at all"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .long("frames")
+                .takes_value(true)
+                .help("Number of frames the headless frontend runs for"),
+        )
+        .arg(
+            Arg::with_name("charset")
+                .long("charset")
+                .takes_value(true)
+                .possible_values(&["halfblock", "braille"])
+                .help("Characters the tty frontend draws pixels with"),
         )
         .arg(
             Arg::with_name("scale")
                 .short("s")
                 .long("scale")
                 .takes_value(true)
-                .help("1x is 64*32"),
+                .help("Initial window size, 1x is 64*32"),
+        )
+        .arg(
+            Arg::with_name("speed")
+                .long("speed")
+                .takes_value(true)
+                .help("Instructions per second"),
+        )
+        .arg(
+            Arg::with_name("timing")
+                .long("timing")
+                .takes_value(true)
+                .possible_values(&Timing::NAMES)
+                .help("How long instructions take, vip charges each its COSMAC VIP machine cycles instead of --speed"),
+        )
+        .arg(
+            Arg::with_name("quirks")
+                .long("quirks")
+                .takes_value(true)
+                .possible_values(&["default", "chip8", "schip", "xochip"])
+                .help("Behaviour of the interpreter the ROM was written for"),
+        )
+        .arg(
+            Arg::with_name("machine")
+                .long("machine")
+                .takes_value(true)
+                .possible_values(&Machine::NAMES)
+                .help("Memory layout of the computer the ROM was written for"),
+        )
+        .arg(
+            Arg::with_name("load-address")
+                .long("load-address")
+                .takes_value(true)
+                .help("Address in hex to load the ROM to and start it at, like 0x600"),
+        )
+        .arg(
+            Arg::with_name("entry-point")
+                .long("entry-point")
+                .takes_value(true)
+                .help("Address in hex to start the ROM at if it differs from the load address"),
+        )
+        .arg(
+            Arg::with_name("ram-size")
+                .long("ram-size")
+                .takes_value(true)
+                .help("Bytes of RAM, like 2048 for 2K systems"),
+        )
+        .arg(
+            Arg::with_name("stack-depth")
+                .long("stack-depth")
+                .takes_value(true)
+                .help("Nested calls before the stack overflows, 12 on the VIP, 16 on the SCHIP or unlimited"),
+        )
+        .arg(
+            Arg::with_name("font")
+                .long("font")
+                .takes_value(true)
+                .help("Hex digit font: vip, dream6800, eti660, schip or a file with 80 or 180 bytes"),
+        )
+        .arg(
+            Arg::with_name("font-address")
+                .long("font-address")
+                .takes_value(true)
+                .help("Address of the font in hex, many interpreters use 0x50"),
+        )
+        .arg(
+            Arg::with_name("keymap")
+                .long("keymap")
+                .takes_value(true)
+                .help("qwertz, qwerty, azerty or the 16 keys for 0 to F like x123qweasdyc4rfv"),
+        )
+        .arg(
+            Arg::with_name("rom-db")
+                .long("rom-db")
+                .takes_value(true)
+                .help("Directory with sha1-hashes.json and programs.json of the chip-8-database"),
+        )
+        .arg(
+            Arg::with_name("no-rom-db")
+                .long("no-rom-db")
+                .help("Don't configure the emulator from the ROM database"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .takes_value(true)
+                .help("Log every executed instruction to a file, - for stderr"),
+        )
+        .arg(
+            Arg::with_name("trace-format")
+                .long("trace-format")
+                .takes_value(true)
+                .possible_values(&["text", "binary"])
+                .help("Format of the trace log"),
+        )
+        .arg(
+            Arg::with_name("trace-range")
+                .long("trace-range")
+                .takes_value(true)
+                .help("Only trace instructions at these addresses, like 200-2FF"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .help("Count cycles per address and subroutine, saved on exit and with F7"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .takes_value(true)
+                .help("Add executed, read and written bytes to this file and save heatmaps next to it"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed for the random numbers of CXNN to make runs repeatable"),
+        )
+        .arg(
+            Arg::with_name("gdb")
+                .long("gdb")
+                .takes_value(true)
+                .value_name("PORT")
+                .help("Hold the ROM until GDB connects on this port to debug it"),
+        )
+        .arg(
+            Arg::with_name("symbols")
+                .long("symbols")
+                .takes_value(true)
+                .help("Label file of the ROM, by default the .sym file next to it"),
+        )
+        .arg(
+            Arg::with_name("break")
+                .long("break")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("LOCATION")
+                .help("Stop at this address or label, like draw+4, when debugging with --gdb"),
+        )
+        .arg(
+            Arg::with_name("cheats")
+                .long("cheats")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Directory with a cheat file per ROM named after its SHA-1 [default: ~/.chip8_emulator_cheats]"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .help("Load the ROM again whenever the file changes"),
+        )
+        .arg(
+            Arg::with_name("watch-restore")
+                .long("watch-restore")
+                .requires("watch")
+                .help("Continue a reloaded ROM where the old one was if its layout stayed the same"),
+        )
+        .arg(
+            Arg::with_name("script")
+                .long("script")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Run a Rhai script that can watch, drive and check the emulation"),
+        )
+        .arg(
+            Arg::with_name("diff-quirks")
+                .long("diff-quirks")
+                .takes_value(true)
+                .possible_values(&["default", "chip8", "schip", "xochip"])
+                .help("Run headless next to these quirks and report where the two runs differ"),
+        )
+        .arg(
+            Arg::with_name("fullscreen")
+                .long("fullscreen")
+                .help("Start in fullscreen, F11 or Alt+Enter toggles it"),
+        )
+        .arg(
+            Arg::with_name("filter")
+                .long("filter")
+                .takes_value(true)
+                .possible_values(&["off", "decay", "blend"])
+                .help("Flicker reduction filter, F2 switches between them while running"),
+        )
+        .arg(
+            Arg::with_name("half-life")
+                .long("half-life")
+                .takes_value(true)
+                .help("Phosphor half-life of the decay filter in milliseconds"),
+        )
+        .arg(
+            Arg::with_name("blend-frames")
+                .long("blend-frames")
+                .takes_value(true)
+                .help("Number of frames the blend filter combines"),
+        )
+        .arg(
+            Arg::with_name("palette")
+                .long("palette")
+                .takes_value(true)
+                .help(
+                    "white, amber, green, lcd or foreground,background as hex like FFB000,1A1000",
+                ),
+        )
+        .arg(
+            Arg::with_name("screenshot-at-frame")
+                .long("screenshot-at-frame")
+                .takes_value(true)
+                .help("Save a PNG screenshot of the unfiltered display at this frame, F12 takes one at any time"),
+        )
+        .arg(
+            Arg::with_name("screenshot-native")
+                .long("screenshot-native")
+                .help("Save screenshots at the resolution of the ROM instead of the current scale"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .help("Record a GIF from the start, F9 starts and stops recording while running"),
+        )
+        .arg(
+            Arg::with_name("dump-frames")
+                .long("dump-frames")
+                .takes_value(true)
+                .help("Directory to dump every frame as PNG together with a WAV of the beeper"),
         )
         .get_matches();
 
-    let screen_scale: u32 = match matches.value_of("scale") {
-        Some(x) => match x.parse() {
-            Ok(y) => y,
-            Err(_) => 1,
+    //Saved settings are only the defaults of the launcher, the command line keeps its own
+    let mut settings = match matches.value_of("file") {
+        Some(_) => Settings {
+            scale: 1,
+            ..Settings::default()
         },
-        None => 1,
+        None => Settings::load(),
     };
+    if let Some(scale) = matches.value_of("scale").and_then(|x| x.parse().ok()) {
+        settings.scale = scale;
+    }
+    if let Some(speed) = matches.value_of("speed").and_then(|x| x.parse().ok()) {
+        settings.speed = speed;
+    }
+    if let Some(quirks) = matches.value_of("quirks") {
+        settings.quirks = quirks.to_string();
+    }
+    if let Some(palette) = matches.value_of("palette") {
+        settings.palette = palette.to_string();
+    }
+    if let Some(keymap) = matches.value_of("keymap") {
+        settings.keymap = keymap.to_string();
+    }
 
     match matches.value_of("file") {
-        Some(f) => match File::open(f) {
-            Ok(file) => emulate(Chip8::create_chip(file, screen_scale)),
-            Err(_) => println!("File doesnt exist"),
-        },
-        None => println!("No File passed"),
+        Some(f) => run(Path::new(f), &settings, &matches),
+        None => {
+            let machine = match machine_choice(&matches) {
+                Ok(machine) => machine,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            if let Some((rom, settings)) = launcher::run(settings, &machine) {
+                if let Err(e) = settings.save() {
+                    println!("Could not save settings: {}", e);
+                }
+                run(&rom, &settings, &matches);
+            }
+        }
     }
 }
 
-fn menu() {
-    let ui = UI::init().unwrap();
-    let mut win =
-        iui::prelude::Window::new(&ui, "Chip-8 Emulator", 200, 200, WindowType::NoMenubar);
+fn run(rom: &Path, settings: &Settings, matches: &ArgMatches) {
+    let palette = match Palette::from_name(&settings.palette) {
+        Some(palette) => palette,
+        None => {
+            println!("Unknown palette {}", settings.palette);
+            return;
+        }
+    };
+    let keymap = match Keymap::from_name(&settings.keymap) {
+        Some(keymap) => keymap,
+        None => {
+            println!("Unknown keymap {}", settings.keymap);
+            return;
+        }
+    };
+    let quirks = match Quirks::from_name(&settings.quirks) {
+        Some(quirks) => quirks,
+        None => {
+            println!("Unknown quirks {}", settings.quirks);
+            return;
+        }
+    };
 
-    // Create a vertical layout to hold the controls
-    let mut vbox = VerticalBox::new(&ui);
-    vbox.set_padded(&ui, true);
+    let filter_mode = matches
+        .value_of("filter")
+        .and_then(FilterMode::from_name)
+        .unwrap_or(FilterMode::Off);
+    let half_life: u64 = matches
+        .value_of("half-life")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(100);
+    let blend_frames: usize = matches
+        .value_of("blend-frames")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(3);
+    let filter = DisplayFilter::new(filter_mode, Duration::from_millis(half_life), blend_frames);
 
-    let mut group_vbox = VerticalBox::new(&ui);
-    let mut group = Group::new(&ui, "Group");
+    let file = match File::open(rom) {
+        Ok(file) => file,
+        Err(_) => {
+            println!("File doesnt exist");
+            return;
+        }
+    };
 
-    // Create two buttons to place in the window
-    let mut button = Button::new(&ui, "Button");
-    button.on_clicked(&ui, {
-        let ui = ui.clone();
-        move |btn| {
-            btn.set_text(&ui, "Clicked!");
+    let machine = match machine_choice(matches) {
+        Ok(machine) => machine,
+        Err(e) => {
+            println!("{}", e);
+            return;
         }
-    });
+    };
+    let load_address = machine.load_address;
 
-    let mut quit_button = Button::new(&ui, "Quit");
-    quit_button.on_clicked(&ui, {
-        let ui = ui.clone();
-        move |_| {
-            ui.quit();
+    let mut chip = match Chip8::create_chip(file, settings.scale, machine.clone()) {
+        Ok(chip) => chip,
+        Err(e) => {
+            println!("Could not load {}: {}", rom.display(), e);
+            return;
+        }
+    };
+    let mut config = RomConfig {
+        quirks,
+        speed: settings.speed,
+        palette,
+    };
+    if let Some(info) = lookup_rom(&chip.rom_hash, matches) {
+        println!(
+            "Recognised {} ({})",
+            info.title,
+            info.platform.as_deref().unwrap_or("unknown platform")
+        );
+        for change in info.configure(&mut config, matches) {
+            println!("{}", change);
+        }
+        for quirk in info.unsupported_quirks.iter() {
+            println!("The ROM needs the {} quirk, which isn't emulated", quirk);
+        }
+        for (action, key) in info.keys.iter() {
+            if let Some(&c) = keymap.keys().get(*key as usize) {
+                println!("{}: {}", action, c);
+            }
         }
-    });
+    }
+    let RomConfig {
+        quirks,
+        speed,
+        palette,
+    } = config;
+    let font = match font_choice(matches) {
+        Ok(font) => font,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let setup = ChipSetup {
+        scale: settings.scale,
+        machine,
+        quirks,
+        timing: matches
+            .value_of("timing")
+            .and_then(Timing::from_name)
+            .unwrap_or_default(),
+        font,
+        seed: matches
+            .value_of("seed")
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_else(rand::random),
+        symbols: symbols_path(rom, matches),
+        breakpoints: matches
+            .values_of("break")
+            .into_iter()
+            .flatten()
+            .map(String::from)
+            .collect(),
+        cheat_directory: matches
+            .value_of("cheats")
+            .map_or_else(Settings::cheat_directory, PathBuf::from),
+    };
+    if let Err(e) = setup.apply(&mut chip) {
+        println!("{}", e);
+        return;
+    }
 
-    // Create a new label. Note that labels don't auto-wrap!
-    let mut label_text = String::new();
-    label_text.push_str("There is a ton of text in this label.\n");
-    label_text.push_str("Pretty much every unicode character is supported.\n");
-    label_text.push_str("🎉 用户界面 사용자 인터페이스");
-    let label = Label::new(&ui, &label_text);
+    let mut tracers: Vec<Box<dyn Tracer>> = Vec::new();
+    if let Some(target) = matches.value_of("trace") {
+        let format = matches
+            .value_of("trace-format")
+            .and_then(TraceFormat::from_name)
+            .unwrap_or(TraceFormat::Text);
+        let range = match matches.value_of("trace-range") {
+            Some(range) => match trace::parse_range(range) {
+                Some(range) => Some(range),
+                None => {
+                    println!("Invalid trace range {}", range);
+                    return;
+                }
+            },
+            None => None,
+        };
+        match TraceWriter::open(target, format, range, chip.symbols.clone()) {
+            Ok(writer) => tracers.push(Box::new(writer)),
+            Err(e) => {
+                println!("Could not open trace {}: {}", target, e);
+                return;
+            }
+        }
+    }
 
-    vbox.append(&ui, label, LayoutStrategy::Stretchy);
-    group_vbox.append(&ui, button, LayoutStrategy::Compact);
-    group_vbox.append(&ui, quit_button, LayoutStrategy::Compact);
-    group.set_child(&ui, group_vbox);
-    vbox.append(&ui, group, LayoutStrategy::Compact);
+    //The ROM was read before, it's only needed again for the listing
+    let profile = if matches.is_present("profile") {
+        let profile = Arc::new(Mutex::new(Profile::new(
+            fs::read(rom).unwrap_or_default(),
+            load_address,
+            chip.symbols.clone(),
+        )));
+        tracers.push(Box::new(Profiler(profile.clone())));
+        Some(profile)
+    } else {
+        None
+    };
+    if !tracers.is_empty() {
+        chip.tracer = Some(Box::new(tracers));
+    }
 
-    // Actually put the button in the window
-    win.set_child(&ui, vbox);
+    let coverage = match matches.value_of("coverage") {
+        Some(path) => {
+            match CoverageOutput::open(
+                Path::new(path),
+                fs::read(rom).unwrap_or_default(),
+                load_address,
+            ) {
+                Ok(output) => {
+                    chip.coverage = Some(output.coverage.clone());
+                    Some(output)
+                }
+                Err(e) => {
+                    println!("Could not open coverage {}: {}", path, e);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
 
-    win.show(&ui);
-    ui.main();
+    let options = Options {
+        rom_name: rom.file_stem().map_or("rom".to_string(), |stem| {
+            stem.to_string_lossy().into_owned()
+        }),
+        palette,
+        keymap,
+        speed: speed.max(1),
+        fullscreen: matches.is_present("fullscreen"),
+        screenshot_at_frame: matches
+            .value_of("screenshot-at-frame")
+            .and_then(|x| x.parse().ok()),
+        screenshot_native: matches.is_present("screenshot-native"),
+        record: matches.value_of("record").map(PathBuf::from),
+        dump_frames: matches.value_of("dump-frames").map(PathBuf::from),
+        profile,
+        coverage,
+        gdb_port: matches.value_of("gdb").and_then(|x| x.parse().ok()),
+        cheat_directory: setup.cheat_directory.clone(),
+    };
+    let frontend = matches.value_of("frontend");
 
-    unsafe {
-        win.destroy();
+    let script = match matches.value_of("script") {
+        Some(path) => {
+            match Script::load(Path::new(path), options.palette, options.rom_name.clone()) {
+                Ok(script) => Some(script),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let diff_quirks = match matches.value_of("diff-quirks") {
+        Some(_) if frontend != Some("headless") => {
+            println!("--diff-quirks needs the headless frontend");
+            return;
+        }
+        Some(name) => match Quirks::from_name(name) {
+            Some(quirks) => Some(quirks),
+            None => {
+                println!("Unknown quirks {}", name);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if matches.is_present("watch") && frontend == Some("headless") {
+        println!("--watch needs the sdl or tty frontend");
+    }
+    let watcher = if matches.is_present("watch") && frontend != Some("headless") {
+        Some(RomWatcher::new(
+            rom,
+            setup.clone(),
+            matches.is_present("watch-restore"),
+        ))
+    } else {
+        None
+    };
+
+    match frontend {
+        Some("tty") => {
+            let charset = matches
+                .value_of("charset")
+                .and_then(tty::Charset::from_name)
+                .unwrap_or(tty::Charset::HalfBlock);
+            tty::emulate(chip, filter, charset, &options, watcher, script)
+        }
+        Some("headless") => {
+            if options.gdb_port.is_some() {
+                println!("--gdb needs the sdl or tty frontend");
+            }
+            let frames = matches
+                .value_of("frames")
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(600);
+            match diff_quirks {
+                Some(other_quirks) => {
+                    if script.is_some() {
+                        println!("--script is ignored with --diff-quirks");
+                    }
+                    let mut other =
+                        match fs::read(rom).map_err(|e| e.to_string()).and_then(|data| {
+                            Chip8::with_machine(setup.machine.clone(), data)
+                                .map_err(|e| e.to_string())
+                        }) {
+                            Ok(other) => other,
+                            Err(e) => {
+                                println!("Could not load {} again: {}", rom.display(), e);
+                                return;
+                            }
+                        };
+                    other.quirks = other_quirks;
+                    other.timing = setup.timing;
+                    if let Some((font, address)) = &setup.font {
+                        if let Err(e) = other.load_font(font, *address) {
+                            println!("Could not load font: {}", e);
+                            return;
+                        }
+                    }
+                    other.seed_rng(setup.seed);
+                    headless::diff(chip, other, frames, &options)
+                }
+                None => headless::emulate(chip, frames, &options, script),
+            }
+        }
+        _ => emulate(chip, filter, &options, watcher, script),
     }
 }
 
-fn emulate(chip: Chip8) {
+//A built-in font or a font file, None keeps the default one
+fn font_choice(matches: &ArgMatches) -> Result<Option<(Font, u16)>, String> {
+    if !matches.is_present("font") && !matches.is_present("font-address") {
+        return Ok(None);
+    }
+    let font = match matches.value_of("font") {
+        Some(name) => Font::from_name(name)
+            .or_else(|| fs::read(name).ok().and_then(|data| Font::from_bytes(&data)))
+            .ok_or_else(|| format!("{} is neither a built-in font nor a font file", name))?,
+        None => Font::default(),
+    };
+    let address = match matches.value_of("font-address") {
+        Some(address) => {
+            parse_address(address).ok_or_else(|| format!("Invalid font address {}", address))?
+        }
+        None => 0,
+    };
+    Ok(Some((font, address)))
+}
+
+//The file given with --symbols, or the ROM's name with .sym if there is such a file
+fn symbols_path(rom: &Path, matches: &ArgMatches) -> Option<PathBuf> {
+    match matches.value_of("symbols") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(rom.with_extension("sym")).filter(|path| path.is_file()),
+    }
+}
+
+//A preset with the addresses given on the command line replaced
+fn machine_choice(matches: &ArgMatches) -> Result<Machine, String> {
+    let name = matches.value_of("machine").unwrap_or("default");
+    let mut machine =
+        Machine::from_name(name).ok_or_else(|| format!("Unknown machine {}", name))?;
+    if let Some(address) = matches.value_of("load-address") {
+        machine.load_address =
+            parse_address(address).ok_or_else(|| format!("Invalid load address {}", address))?;
+        machine.entry_point = machine.load_address;
+    }
+    if let Some(address) = matches.value_of("entry-point") {
+        machine.entry_point =
+            parse_address(address).ok_or_else(|| format!("Invalid entry point {}", address))?;
+    }
+    if let Some(size) = matches.value_of("ram-size") {
+        machine.ram_size = size
+            .parse()
+            .ok()
+            .filter(|&size| size <= Machine::MAX_RAM_SIZE)
+            .ok_or_else(|| format!("RAM size {} is not between 0 and 4096", size))?;
+    }
+    if let Some(depth) = matches.value_of("stack-depth") {
+        machine.stack_depth = match depth {
+            "unlimited" => None,
+            _ => Some(
+                depth
+                    .parse()
+                    .ok()
+                    .filter(|&depth| depth > 0)
+                    .ok_or_else(|| format!("Invalid stack depth {}", depth))?,
+            ),
+        };
+    }
+    Ok(machine)
+}
+
+//Hex with or without 0x in front
+fn parse_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.strip_prefix("0x").unwrap_or(text), 16).ok()
+}
+
+fn lookup_rom(hash: &str, matches: &ArgMatches) -> Option<RomInfo> {
+    if matches.is_present("no-rom-db") {
+        return None;
+    }
+    let database = match matches.value_of("rom-db") {
+        Some(directory) => match RomDatabase::load(Path::new(directory)) {
+            Ok(database) => database,
+            Err(e) => {
+                println!("Could not load ROM database from {}: {}", directory, e);
+                return None;
+            }
+        },
+        None => RomDatabase::bundled(),
+    };
+    database.lookup(hash)
+}
+
+fn emulate(
+    chip: Chip8,
+    mut filter: DisplayFilter,
+    options: &Options,
+    mut watcher: Option<RomWatcher>,
+    script: Option<Script>,
+) {
     //Todo check if better solution for this exists
     let (mut event_pump, mut canvas) = init_sdl(
         Chip8::SCREEN_WIDTH as u32,
         Chip8::SCREEN_HEIGHT as u32,
         chip.screen_scale,
     );
+    if options.fullscreen {
+        toggle_fullscreen(&mut canvas);
+    }
     let texture_creator = canvas.texture_creator();
+    let mut texture_size = (Chip8::SCREEN_WIDTH, Chip8::SCREEN_HEIGHT);
     let mut texture = texture_creator
         .create_texture_static(
             PixelFormatEnum::ABGR8888,
-            Chip8::SCREEN_WIDTH as u32,
-            Chip8::SCREEN_HEIGHT as u32,
+            texture_size.0 as u32,
+            texture_size.1 as u32,
         )
         .unwrap();
 
-    let gfx = Arc::new(Mutex::new(
-        [[false; Chip8::SCREEN_WIDTH]; Chip8::SCREEN_HEIGHT],
-    ));
-    let keys = Arc::new(Mutex::new([false; 16]));
+    let shared = Shared::default();
+    let mut overlay = Overlay::new(chip.symbols.clone());
+    let (reloads, reloaded) = mpsc::channel();
 
-    start_logic_thread(chip, keys.clone(), gfx.clone());
+    let logic = start_logic_thread(chip, options, shared.clone(), reloaded, script);
 
-    while !quit_event_activated(&mut event_pump) {
+    let mut last_frame = Instant::now();
+    let mut frame: u64 = 0;
+    let mut recording = Recording::start(options);
+
+    'running: loop {
         //TODO try to make a render function out of this if texture lets me
         let before_cycle = Instant::now();
+        let mut screenshot_requested = options.screenshot_at_frame == Some(frame);
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => filter.cycle_mode(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => toggle_fullscreen(&mut canvas),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    toggle_fullscreen(&mut canvas)
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => screenshot_requested = true,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => recording.toggle_gif(options, frame),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    repeat: false,
+                    ..
+                } => save_reports(options),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => toggle_overlay(&mut canvas, &mut overlay, View::Panels),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    repeat: false,
+                    ..
+                } => toggle_overlay(&mut canvas, &mut overlay, View::Sprites),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    ..
+                } if overlay.visible => overlay.key_down(keycode, keymod),
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if overlay.visible => overlay.click(x, y, canvas.output_size().unwrap()),
+                _ => {}
+            }
+        }
+
+        if shared.quit.load(Ordering::Relaxed) {
+            break 'running;
+        }
 
-        *keys.lock().unwrap() = map_keys(&mut event_pump);
+        let pressed = map_keys(&mut event_pump, &options.keymap);
+        *shared.keys.lock().unwrap() = pressed;
 
-        let pixel_data = update_gfx(
-            &gfx.lock().unwrap().clone(),
-            Chip8::SCREEN_WIDTH,
-            Chip8::SCREEN_HEIGHT,
-        );
+        if let Some(reload) = watcher.as_mut().and_then(RomWatcher::poll) {
+            overlay.symbols = reload.chip.symbols.clone();
+            reloads.send(reload).unwrap();
+        }
+
+        if overlay.visible {
+            if let Some(state) = shared.machine_state.take() {
+                overlay.update(state);
+            }
+            shared.machine_state.request();
+        }
 
-        texture.update(None, &pixel_data[..], PITCH).unwrap();
+        let current_gfx = *shared.gfx.lock().unwrap();
+        let intensities = filter.apply(&current_gfx, last_frame.elapsed());
+        last_frame = Instant::now();
 
-        canvas.copy(&texture, None, None).unwrap();
+        //The SCHIP switches between 64x32 and 128x64 while running
+        let gfx_size = (current_gfx.width(), current_gfx.height());
+        if gfx_size != texture_size {
+            texture_size = gfx_size;
+            texture = texture_creator
+                .create_texture_static(
+                    PixelFormatEnum::ABGR8888,
+                    texture_size.0 as u32,
+                    texture_size.1 as u32,
+                )
+                .unwrap();
+        }
+        let pixel_data = update_gfx(&intensities, &options.palette, gfx_size.0, gfx_size.1);
+
+        texture
+            .update(None, &pixel_data[..], gfx_size.0 * 4)
+            .unwrap();
+
+        //Derive the presentation from the texture so a change of resolution is picked up
+        let query = texture.query();
+        let output_size = canvas.output_size().unwrap();
+        let (game_area, panel_area, text_scale) = overlay.layout(output_size);
+        let game_size = if overlay.visible {
+            (game_area.width(), game_area.height())
+        } else {
+            output_size
+        };
+        let target = letterbox(game_size, (query.width, query.height));
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        canvas.copy(&texture, None, target).unwrap();
+        if overlay.visible {
+            overlay.draw(&mut canvas, panel_area, text_scale, &pressed);
+        }
         canvas.present();
 
-        let time_to_wait = 16_666_666_u128.saturating_sub(before_cycle.elapsed().as_nanos()); //60Fps
+        if screenshot_requested {
+            let message =
+                take_screenshot(options, frame, &current_gfx, target.width() / query.width);
+            eprintln!("{}", message);
+        }
+        recording.add_frame(&current_gfx, shared.sound.load(Ordering::Relaxed));
+        frame += 1;
+
+        let time_to_wait = FRAME_TIME_NS.saturating_sub(before_cycle.elapsed().as_nanos());
         thread::sleep(Duration::new(0, time_to_wait as u32));
     }
+
+    stop_logic_thread(&shared, reloads, logic);
+    recording.finish();
+    save_reports(options);
+}
+
+//Waits for the chip to be dropped, which flushes the end of the trace
+pub fn stop_logic_thread(shared: &Shared, reloads: Sender<Reload>, logic: JoinHandle<()>) {
+    shared.quit.store(true, Ordering::Relaxed);
+    //Wakes a chip that stopped with an error and waits for a reload
+    drop(reloads);
+    if logic.join().is_err() {
+        eprintln!("The emulation thread panicked");
+    }
+}
+
+//Profile and coverage, whichever are collected
+pub fn save_reports(options: &Options) {
+    if let Some(profile) = &options.profile {
+        match profile.lock().unwrap().write_reports(&options.rom_name) {
+            Ok(()) => println!(
+                "Saved profile {}_profile.txt, .folded and .asm",
+                options.rom_name
+            ),
+            Err(e) => println!("Could not save profile: {}", e),
+        }
+    }
+    if let Some(coverage) = &options.coverage {
+        match coverage.save() {
+            Ok(()) => println!("Saved coverage {}", coverage.path.display()),
+            Err(e) => println!("Could not save coverage: {}", e),
+        }
+    }
+}
+
+//Saves gfx as it is, without the display filter, and returns what happened for the frontend to show
+fn take_screenshot(options: &Options, frame: u64, gfx: &Gfx, scale: u32) -> String {
+    let path = screenshot::file_name(&options.rom_name, frame);
+    let scale = if options.screenshot_native { 1 } else { scale };
+    match screenshot::save_png(&path, gfx, &options.palette, scale) {
+        Ok(()) => format!("Saved screenshot {}", path.display()),
+        Err(e) => format!("Could not save screenshot {}: {}", path.display(), e),
+    }
 }
 
 fn start_logic_thread(
     mut chip: Chip8,
-    keys: Arc<Mutex<[bool; 16]>>,
-    gfx: Arc<Mutex<[[bool; Chip8::SCREEN_WIDTH]; Chip8::SCREEN_HEIGHT]>>,
-) {
+    options: &Options,
+    shared: Shared,
+    reloads: Receiver<Reload>,
+    mut script: Option<Script>,
+) -> JoinHandle<()> {
+    let mut scheduler = options.scheduler();
+    if let Some(port) = options.gdb_port {
+        if script.is_some() {
+            println!("--script is ignored with --gdb");
+        }
+        match gdb::listen(port) {
+            Ok(listener) => return gdb::start(listener, chip, options, shared, reloads),
+            Err(e) => println!("Could not start GDB stub on port {}: {}", port, e),
+        }
+    }
+
+    if let Some(script) = &mut script {
+        script.start(&mut chip);
+    }
+
     thread::spawn(move || loop {
         let before_cycle = Instant::now();
 
-        chip.key_pressed = keys.lock().unwrap().clone();
-        chip.emulate_cycle();
-        *gfx.lock().unwrap() = chip.gfx.clone();
+        if shared.quit.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Ok(reload) = reloads.try_recv() {
+            reload.apply(&mut chip);
+        }
+        chip.key_pressed = *shared.keys.lock().unwrap();
+        if let Some(script) = &script {
+            script.press_keys(&mut chip.key_pressed);
+        }
+        let step = match scheduler.step(&mut chip) {
+            Ok(step) => step,
+            Err(e) => {
+                println!("Emulation stopped: {}", e);
+                //A fixed ROM can still be loaded, without --watch this waits until the window closes
+                match reloads.recv() {
+                    Ok(reload) => reload.apply(&mut chip),
+                    Err(_) => break,
+                }
+                continue;
+            }
+        };
+        if let Some(script) = &mut script {
+            script.after_cycle(&mut chip);
+        }
+        if step.frame_ended {
+            chip.apply_cheats();
+            if let Some(script) = &mut script {
+                script.end_frame(&mut chip);
+                if script.quit_requested() {
+                    shared.quit.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        *shared.gfx.lock().unwrap() = chip.gfx;
+        shared.sound.store(chip.sound_active(), Ordering::Relaxed);
+        shared.machine_state.fulfil(&chip);
 
-        let time_to_wait = 50000_u128.saturating_sub(before_cycle.elapsed().as_nanos());
-        thread::sleep(Duration::new(0, time_to_wait as u32));
+        thread::sleep(
+            step.duration
+                .checked_sub(before_cycle.elapsed())
+                .unwrap_or_default(),
+        );
         //println!("{}", time_to_wait);
-    });
+    })
 }
 
 fn init_sdl(
@@ -189,11 +1108,13 @@ fn init_sdl(
             screen_scale * screen_height,
         )
         .position_centered()
+        .resizable()
         .build()
         .unwrap();
     let mut canvas = window.into_canvas().build().unwrap();
     canvas
-        .set_scale(screen_scale as f32, screen_scale as f32)
+        .window_mut()
+        .set_minimum_size(screen_width, screen_height)
         .unwrap();
 
     let event_pump = sdl_context.event_pump().unwrap();
@@ -201,18 +1122,53 @@ fn init_sdl(
     (event_pump, canvas)
 }
 
-fn quit_event_activated(event_pump: &mut EventPump) -> bool {
-    event_pump.poll_iter().any(|x| {
-        if let Event::Quit { .. } = x {
-            true
-        } else {
-            false
-        }
-    })
+//Outside of fullscreen the window grows by the panels so the game keeps its size
+fn toggle_overlay(canvas: &mut Canvas<Window>, overlay: &mut Overlay, view: View) {
+    let was_visible = overlay.visible;
+    overlay.toggle_view(view);
+    let window = canvas.window_mut();
+    if overlay.visible == was_visible || window.fullscreen_state() != FullscreenType::Off {
+        return;
+    }
+    let (width, height) = window.size();
+    let width = if overlay.visible {
+        width + Overlay::width()
+    } else {
+        width
+            .saturating_sub(Overlay::width())
+            .max(Chip8::SCREEN_WIDTH as u32)
+    };
+    window.set_size(width, height).unwrap();
+}
+
+fn toggle_fullscreen(canvas: &mut Canvas<Window>) {
+    let window = canvas.window_mut();
+    let next_state = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    window.set_fullscreen(next_state).unwrap();
+}
+
+//Largest integer scale that fits the output, centered with black bars around it
+fn letterbox(output_size: (u32, u32), native_size: (u32, u32)) -> Rect {
+    let (output_width, output_height) = output_size;
+    let (native_width, native_height) = native_size;
+    let scale = (output_width / native_width)
+        .min(output_height / native_height)
+        .max(1);
+    let width = native_width * scale;
+    let height = native_height * scale;
+
+    Rect::new(
+        (output_width as i32 - width as i32) / 2,
+        (output_height as i32 - height as i32) / 2,
+        width,
+        height,
+    )
 }
 
-fn map_keys(event_pump: &mut EventPump) -> [bool; 16] {
-    //Todo find a better solution for this and put the keys in a config file
+fn map_keys(event_pump: &mut EventPump, keymap: &Keymap) -> [bool; 16] {
     let keys: HashSet<Keycode> = event_pump
         .keyboard_state()
         .pressed_scancodes()
@@ -221,25 +1177,12 @@ fn map_keys(event_pump: &mut EventPump) -> [bool; 16] {
 
     let mut key_pressed = [false; 16];
 
+    //Keycodes of printable keys are their ascii characters
     for key in keys {
-        match key {
-            Keycode::Num1 => key_pressed[1] = true,
-            Keycode::Num2 => key_pressed[2] = true,
-            Keycode::Num3 => key_pressed[3] = true,
-            Keycode::Num4 => key_pressed[0xC] = true,
-            Keycode::Q => key_pressed[4] = true,
-            Keycode::W => key_pressed[5] = true,
-            Keycode::E => key_pressed[6] = true,
-            Keycode::R => key_pressed[0xD] = true,
-            Keycode::A => key_pressed[7] = true,
-            Keycode::S => key_pressed[8] = true,
-            Keycode::D => key_pressed[9] = true,
-            Keycode::F => key_pressed[0xE] = true,
-            Keycode::Y => key_pressed[0xA] = true,
-            Keycode::X => key_pressed[0] = true,
-            Keycode::C => key_pressed[0xB] = true,
-            Keycode::V => key_pressed[0xF] = true,
-            _ => {}
+        if let Some(index) =
+            std::char::from_u32(key as i32 as u32).and_then(|c| keymap.key_index(c))
+        {
+            key_pressed[index] = true;
         }
     }
 
@@ -247,7 +1190,8 @@ fn map_keys(event_pump: &mut EventPump) -> [bool; 16] {
 }
 
 fn update_gfx(
-    emulator_gfx: &[[bool; Chip8::SCREEN_WIDTH]; Chip8::SCREEN_HEIGHT],
+    intensities: &Intensities,
+    palette: &Palette,
     screen_width: usize,
     screen_height: usize,
 ) -> Vec<u8> {
@@ -255,7 +1199,9 @@ fn update_gfx(
 
     for i in 0..screen_height {
         for j in 0..screen_width {
-            gfx[i * screen_width + j] = emulator_gfx[i][j] as u32 * 0xFFFF_FFFF;
+            //ABGR with an opaque alpha channel
+            let [r, g, b] = palette.colour(intensities[i][j]);
+            gfx[i * screen_width + j] = 0xFF00_0000 | (b as u32) << 16 | (g as u32) << 8 | r as u32;
         }
     }
 
@@ -0,0 +1,432 @@
+use crate::chip8::{disassemble_with, Chip8, Core, MachineState, SpriteDraw, Symbols};
+use crate::sprite_viewer::SpriteViewer;
+use crate::text::{draw_text, CELL_HEIGHT, CELL_WIDTH};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+const COLUMN_CHARS: u32 = 30;
+const COLUMN_LINES: u32 = 34;
+const PADDING: u32 = 4;
+const MEMORY_ROWS: usize = 8;
+const MEMORY_COLUMNS: usize = 8;
+const DISASSEMBLY_LINES: u16 = 11;
+const CALL_STACK_LINES: usize = 12;
+//Bytes stay highlighted for this many snapshots after they changed
+const WRITE_FADE_FRAMES: u8 = 60;
+const KEYPAD: [[usize; 4]; 4] = [[1, 2, 3, 12], [4, 5, 6, 13], [7, 8, 9, 14], [10, 0, 11, 15]];
+
+const BACKGROUND: Color = Color {
+    r: 24,
+    g: 24,
+    b: 32,
+    a: 255,
+};
+const TITLE: Color = Color {
+    r: 240,
+    g: 200,
+    b: 80,
+    a: 255,
+};
+const TEXT: Color = Color {
+    r: 200,
+    g: 200,
+    b: 200,
+    a: 255,
+};
+const HIGHLIGHT: Color = Color {
+    r: 70,
+    g: 70,
+    b: 110,
+    a: 255,
+};
+
+pub struct Snapshot {
+    pub machine: MachineState,
+    pub last_sprite: Option<SpriteDraw>,
+    pub font_address: u16,
+    pub call_stack: Vec<String>,
+}
+
+//The logic thread owns the chip, so the render loop asks it for a copy of the state once a frame
+#[derive(Clone, Default)]
+pub struct StateMailbox {
+    requested: Arc<AtomicBool>,
+    state: Arc<Mutex<Option<Snapshot>>>,
+}
+
+impl StateMailbox {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn fulfil(&self, chip: &Chip8) {
+        if self.requested.swap(false, Ordering::Relaxed) {
+            *self.state.lock().unwrap() = Some(Snapshot {
+                machine: chip.state(),
+                last_sprite: chip.last_sprite,
+                font_address: chip.font_address(),
+                call_stack: chip.call_stack_labels(),
+            });
+        }
+    }
+
+    pub fn take(&self) -> Option<Snapshot> {
+        self.state.lock().unwrap().take()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum View {
+    Panels,
+    Sprites,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Panel {
+    Registers,
+    Timers,
+    Keypad,
+    CallStack,
+    Disassembly,
+    Memory,
+}
+
+//Left column first, Ctrl+1 to Ctrl+6 toggle them in this order
+const PANELS: [(Panel, usize); 6] = [
+    (Panel::Registers, 0),
+    (Panel::Timers, 0),
+    (Panel::Keypad, 0),
+    (Panel::CallStack, 0),
+    (Panel::Disassembly, 1),
+    (Panel::Memory, 1),
+];
+
+//Debug panels or the sprite viewer drawn to the right of the game, fed with snapshots from a
+//StateMailbox
+pub struct Overlay {
+    pub visible: bool,
+    pub view: View,
+    shown: [bool; 6],
+    state: Option<MachineState>,
+    last_sprite: Option<SpriteDraw>,
+    font_address: u16,
+    call_stack: Vec<String>,
+    pub symbols: Symbols,
+    write_age: [u8; 4096],
+    //None follows the I register
+    memory_view: Option<u16>,
+    sprites: SpriteViewer,
+}
+
+impl Overlay {
+    pub fn new(symbols: Symbols) -> Overlay {
+        Overlay {
+            visible: false,
+            view: View::Panels,
+            shown: [true; 6],
+            state: None,
+            last_sprite: None,
+            font_address: 0,
+            call_stack: Vec::new(),
+            symbols,
+            write_age: [WRITE_FADE_FRAMES; 4096],
+            memory_view: None,
+            sprites: SpriteViewer::new(),
+        }
+    }
+
+    //Hides the overlay if this view is already shown
+    pub fn toggle_view(&mut self, view: View) {
+        self.visible = !(self.visible && self.view == view);
+        self.view = view;
+    }
+
+    pub fn key_down(&mut self, keycode: Keycode, keymod: Mod) {
+        match self.view {
+            View::Panels => match keycode {
+                Keycode::PageUp => self.scroll_memory(-8),
+                Keycode::PageDown => self.scroll_memory(8),
+                Keycode::Home => self.memory_view = None,
+                _ if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    //Ctrl+1 to Ctrl+6 show and hide the single panels
+                    let n = keycode as i32 - Keycode::Num1 as i32;
+                    if (0..6).contains(&n) {
+                        self.shown[n as usize] = !self.shown[n as usize];
+                    }
+                }
+                _ => {}
+            },
+            View::Sprites => self
+                .sprites
+                .key_down(keycode, self.last_sprite, self.font_address),
+        }
+    }
+
+    pub fn click(&mut self, x: i32, y: i32, output_size: (u32, u32)) {
+        if self.view == View::Sprites {
+            let (_, area, scale) = self.layout(output_size);
+            self.sprites.click(x, y, area, scale);
+        }
+    }
+
+    fn scroll_memory(&mut self, rows: i32) {
+        let start = self.memory_start() as i32 + rows * MEMORY_COLUMNS as i32;
+        let last = (4096 - MEMORY_ROWS * MEMORY_COLUMNS) as i32;
+        self.memory_view = Some(start.max(0).min(last) as u16);
+    }
+
+    pub fn update(&mut self, snapshot: Snapshot) {
+        let state = snapshot.machine;
+        if let Some(previous) = &self.state {
+            for (address, age) in self.write_age.iter_mut().enumerate() {
+                *age = if previous.memory[address] != state.memory[address] {
+                    0
+                } else {
+                    age.saturating_add(1).min(WRITE_FADE_FRAMES)
+                };
+            }
+        }
+        self.state = Some(state);
+        self.last_sprite = snapshot.last_sprite;
+        self.font_address = snapshot.font_address;
+        self.call_stack = snapshot.call_stack;
+    }
+
+    //Width of the two panel columns at a text scale of 1
+    pub fn width() -> u32 {
+        2 * (COLUMN_CHARS * CELL_WIDTH + 2 * PADDING)
+    }
+
+    //Splits the output into the game area and the panels with the largest text that fits
+    pub fn layout(&self, output_size: (u32, u32)) -> (Rect, Rect, u32) {
+        let (width, height) = output_size;
+        let scale = (height / (COLUMN_LINES * CELL_HEIGHT + 2 * PADDING))
+            .min(width / 2 / Overlay::width())
+            .max(1);
+        let panel_width = (Overlay::width() * scale).min(width.saturating_sub(1));
+        let game = Rect::new(0, 0, width - panel_width, height);
+        let panels = Rect::new((width - panel_width) as i32, 0, panel_width, height);
+        (game, panels, scale)
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, area: Rect, scale: u32, keys: &[bool; 16]) {
+        canvas.set_draw_color(BACKGROUND);
+        canvas.fill_rect(area).unwrap();
+        if self.view == View::Sprites {
+            if let Some(state) = &self.state {
+                self.sprites
+                    .draw(canvas, area, scale, &state.memory, self.last_sprite);
+            }
+            return;
+        }
+
+        let column_width = (COLUMN_CHARS * CELL_WIDTH + 2 * PADDING) * scale;
+        let mut lines = [0; 2];
+        for (index, &(panel, column)) in PANELS.iter().enumerate() {
+            if !self.shown[index] {
+                continue;
+            }
+            let mut writer = PanelWriter {
+                canvas,
+                x: area.x() + ((column as u32 * column_width + PADDING * scale) as i32),
+                y: area.y() + (PADDING * scale) as i32,
+                line: lines[column],
+                scale,
+            };
+            self.draw_panel(&mut writer, panel, keys);
+            lines[column] = writer.line + 1;
+        }
+    }
+
+    fn draw_panel(&self, writer: &mut PanelWriter, panel: Panel, keys: &[bool; 16]) {
+        writer.title(match panel {
+            Panel::Registers => "REGISTERS",
+            Panel::Timers => "TIMERS",
+            Panel::Keypad => "KEYPAD",
+            Panel::CallStack => "CALL STACK",
+            Panel::Disassembly => "DISASSEMBLY",
+            Panel::Memory => "MEMORY",
+        });
+        if panel == Panel::Keypad {
+            for row in KEYPAD.iter() {
+                for (n, &key) in row.iter().enumerate() {
+                    if keys[key] {
+                        writer.highlight(n * 3, 3, HIGHLIGHT);
+                    }
+                    writer.text(n * 3, &format!(" {:X}", key));
+                }
+                writer.line += 1;
+            }
+            return;
+        }
+
+        let state = match &self.state {
+            Some(state) => state,
+            None => return writer.line("WAITING FOR THE CHIP"),
+        };
+        match panel {
+            Panel::Registers => {
+                for (n, values) in state.v.chunks(4).enumerate() {
+                    let registers: Vec<String> = values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| format!("V{:X} {:02X}", n * 4 + i, value))
+                        .collect();
+                    writer.line(&registers.join("  "));
+                }
+                writer.line(&format!(
+                    "I {:04X}  PC {:04X}  SP {}",
+                    state.i_reg,
+                    state.pc,
+                    state.stack.len()
+                ));
+            }
+            Panel::Timers => writer.line(&format!(
+                "DT {:02X}  ST {:02X}",
+                state.delay_timer, state.sound_timer
+            )),
+            Panel::CallStack => {
+                if self.call_stack.is_empty() {
+                    writer.line("EMPTY");
+                }
+                //Innermost call first, an unlimited stack could fill the whole column
+                for (depth, location) in self
+                    .call_stack
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .take(CALL_STACK_LINES)
+                {
+                    writer.line(&clip(&format!("{:2} RET TO {}", depth, location)));
+                }
+                if self.call_stack.len() > CALL_STACK_LINES {
+                    writer.line(&format!(
+                        "   {} MORE",
+                        self.call_stack.len() - CALL_STACK_LINES
+                    ));
+                }
+            }
+            Panel::Disassembly => {
+                let first = state.pc.saturating_sub(DISASSEMBLY_LINES / 2 * 2);
+                let last_line = writer.line + DISASSEMBLY_LINES as u32;
+                for address in (first..4095).step_by(2) {
+                    if writer.line >= last_line {
+                        break;
+                    }
+                    //Labels get a line of their own if the instruction still fits below
+                    match self.symbols.label_at(address) {
+                        Some(label) if writer.line + 1 < last_line => {
+                            writer.line(&clip(&format!("{}:", label)))
+                        }
+                        _ => {}
+                    }
+                    let opcode = u16::from_be_bytes([
+                        state.memory[address as usize],
+                        state.memory[address as usize + 1],
+                    ]);
+                    if address == state.pc {
+                        writer.highlight(0, COLUMN_CHARS as usize, HIGHLIGHT);
+                    }
+                    writer.line(&clip(&format!(
+                        "{} {:04X} {:04X} {}",
+                        if address == state.pc { '>' } else { ' ' },
+                        address,
+                        opcode,
+                        disassemble_with(opcode, &self.symbols)
+                    )));
+                }
+            }
+            Panel::Memory => {
+                let start = self.memory_start() as usize;
+                if self.memory_view.is_none() {
+                    writer.line("FOLLOWING I, PGUP/PGDN SCROLL");
+                } else {
+                    writer.line("HOME FOLLOWS I");
+                }
+                for row in 0..MEMORY_ROWS {
+                    let address = start + row * MEMORY_COLUMNS;
+                    for column in 0..MEMORY_COLUMNS {
+                        let age = self.write_age[address + column];
+                        if age < WRITE_FADE_FRAMES {
+                            let heat = 255 - (age as u32 * 180 / WRITE_FADE_FRAMES as u32) as u8;
+                            writer.highlight(5 + column * 3, 2, Color::RGB(heat, 40, 40));
+                        }
+                    }
+                    let bytes: Vec<String> = state.memory[address..address + MEMORY_COLUMNS]
+                        .iter()
+                        .map(|byte| format!("{:02X}", byte))
+                        .collect();
+                    writer.line(&format!("{:04X} {}", address, bytes.join(" ")));
+                }
+            }
+            Panel::Keypad => {}
+        }
+    }
+
+    fn memory_start(&self) -> u16 {
+        let last = (4096 - MEMORY_ROWS * MEMORY_COLUMNS) as u16;
+        match (self.memory_view, &self.state) {
+            (Some(start), _) => start,
+            (None, Some(state)) => (state.i_reg & !(MEMORY_COLUMNS as u16 - 1)).min(last),
+            (None, None) => 0,
+        }
+    }
+}
+
+//Lays out text line by line in a column of character cells
+//Long labels would run into the next column
+fn clip(text: &str) -> String {
+    text.chars().take(COLUMN_CHARS as usize).collect()
+}
+
+struct PanelWriter<'a> {
+    canvas: &'a mut Canvas<Window>,
+    x: i32,
+    y: i32,
+    line: u32,
+    scale: u32,
+}
+
+impl PanelWriter<'_> {
+    fn position(&self, column: usize) -> (i32, i32) {
+        (
+            self.x + (column as u32 * CELL_WIDTH * self.scale) as i32,
+            self.y + (self.line * CELL_HEIGHT * self.scale) as i32,
+        )
+    }
+
+    fn text(&mut self, column: usize, text: &str) {
+        let (x, y) = self.position(column);
+        draw_text(self.canvas, text, x, y, self.scale, TEXT);
+    }
+
+    fn title(&mut self, text: &str) {
+        let (x, y) = self.position(0);
+        draw_text(self.canvas, text, x, y, self.scale, TITLE);
+        self.line += 1;
+    }
+
+    fn line(&mut self, text: &str) {
+        self.text(0, text);
+        self.line += 1;
+    }
+
+    //Background for characters of the current line, drawn before their text
+    fn highlight(&mut self, column: usize, len: usize, colour: Color) {
+        let (x, y) = self.position(column);
+        self.canvas.set_draw_color(colour);
+        self.canvas
+            .fill_rect(Rect::new(
+                x - self.scale as i32,
+                y - self.scale as i32,
+                len as u32 * CELL_WIDTH * self.scale,
+                CELL_HEIGHT * self.scale,
+            ))
+            .unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Palette {
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+}
+
+impl Palette {
+    pub const PRESETS: [(&'static str, Palette); 4] = [
+        (
+            "white",
+            Palette {
+                foreground: [0xFF, 0xFF, 0xFF],
+                background: [0x00, 0x00, 0x00],
+            },
+        ),
+        (
+            "amber",
+            Palette {
+                foreground: [0xFF, 0xB0, 0x00],
+                background: [0x1A, 0x10, 0x00],
+            },
+        ),
+        (
+            "green",
+            Palette {
+                foreground: [0x33, 0xFF, 0x66],
+                background: [0x00, 0x1A, 0x08],
+            },
+        ),
+        (
+            "lcd",
+            Palette {
+                foreground: [0x0F, 0x38, 0x0F],
+                background: [0x9B, 0xBC, 0x0F],
+            },
+        ),
+    ];
+
+    //Either the name of a preset or two hex colours like "FFB000,1A1000"
+    pub fn from_name(name: &str) -> Option<Palette> {
+        if let Some((_, palette)) = Palette::PRESETS.iter().find(|(n, _)| *n == name) {
+            return Some(*palette);
+        }
+
+        let mut colours = name.split(',').map(parse_hex_colour);
+        match (colours.next(), colours.next(), colours.next()) {
+            (Some(Some(foreground)), Some(Some(background)), None) => Some(Palette {
+                foreground,
+                background,
+            }),
+            _ => None,
+        }
+    }
+
+    //Mixes background and foreground, 0 is fully background and 255 fully foreground
+    pub fn colour(&self, intensity: u8) -> [u8; 3] {
+        let mut colour = [0; 3];
+        for (c, (&fg, &bg)) in colour
+            .iter_mut()
+            .zip(self.foreground.iter().zip(self.background.iter()))
+        {
+            let mixed = bg as i32 + (fg as i32 - bg as i32) * intensity as i32 / 255;
+            *c = mixed as u8;
+        }
+        colour
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::PRESETS[0].1
+    }
+}
+
+pub fn parse_hex_colour(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
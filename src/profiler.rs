@@ -0,0 +1,231 @@
+use crate::chip8::{disassemble_with, pattern, Symbols, TraceEntry, Tracer};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+const REPORT_ROWS: usize = 30;
+
+#[derive(Default)]
+struct CallStats {
+    calls: u64,
+    cycles: u64,
+}
+
+//Cycles per PC, per instruction type and per call stack, fed by the trace hook of the chip
+pub struct Profile {
+    rom: Vec<u8>,
+    load_address: u16,
+    symbols: Symbols,
+    cycles: u64,
+    pc_counts: HashMap<u16, u64>,
+    opcodes: HashMap<u16, u16>,
+    pattern_counts: HashMap<&'static str, u64>,
+    //Entry points of the active subroutines and the cycles they were called at
+    stack: Vec<u16>,
+    called_at: Vec<u64>,
+    folded: HashMap<Vec<u16>, u64>,
+    subroutines: HashMap<u16, CallStats>,
+}
+
+impl Profile {
+    pub fn new(rom: Vec<u8>, load_address: u16, symbols: Symbols) -> Profile {
+        Profile {
+            rom,
+            load_address,
+            symbols,
+            cycles: 0,
+            pc_counts: HashMap::new(),
+            opcodes: HashMap::new(),
+            pattern_counts: HashMap::new(),
+            stack: Vec::new(),
+            called_at: Vec::new(),
+            folded: HashMap::new(),
+            subroutines: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, entry: &TraceEntry) {
+        self.cycles += 1;
+        *self.pc_counts.entry(entry.pc).or_insert(0) += 1;
+        self.opcodes.insert(entry.pc, entry.opcode);
+        *self
+            .pattern_counts
+            .entry(pattern(entry.opcode))
+            .or_insert(0) += 1;
+
+        match self.folded.get_mut(&self.stack[..]) {
+            Some(count) => *count += 1,
+            None => {
+                self.folded.insert(self.stack.clone(), 1);
+            }
+        }
+
+        if entry.opcode & 0xF000 == 0x2000 {
+            self.stack.push(entry.opcode & 0x0FFF);
+            self.called_at.push(self.cycles);
+        } else if entry.opcode == 0x00EE {
+            if let (Some(address), Some(called_at)) = (self.stack.pop(), self.called_at.pop()) {
+                let stats = self.subroutines.entry(address).or_default();
+                stats.calls += 1;
+                stats.cycles += self.cycles - called_at;
+            }
+        }
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.cycles.max(1) as f64
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!("{} cycles\n\nHottest addresses\n", self.cycles);
+        let mut pcs: Vec<(&u16, &u64)> = self.pc_counts.iter().collect();
+        pcs.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (pc, count) in pcs.iter().take(REPORT_ROWS) {
+            report.push_str(&format!(
+                "{:>12} {:>6.2}%  {}  {}\n",
+                count,
+                self.percent(**count),
+                self.symbols.describe(**pc),
+                disassemble_with(self.opcodes[pc], &self.symbols)
+            ));
+        }
+
+        report.push_str("\nInstruction types\n");
+        let mut patterns: Vec<(&&str, &u64)> = self.pattern_counts.iter().collect();
+        patterns.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (pattern, count) in patterns {
+            report.push_str(&format!(
+                "{:>12} {:>6.2}%  {}\n",
+                count,
+                self.percent(*count),
+                pattern
+            ));
+        }
+
+        //Subroutines that haven't returned yet are missing, their time isn't known
+        report.push_str("\nSubroutines (calls, cycles including callees)\n");
+        let mut subroutines: Vec<(&u16, &CallStats)> = self.subroutines.iter().collect();
+        subroutines.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(b.0)));
+        for (address, stats) in subroutines.iter().take(REPORT_ROWS) {
+            report.push_str(&format!(
+                "{:>12} {:>6.2}%  {}  {} calls\n",
+                stats.cycles,
+                self.percent(stats.cycles),
+                self.symbols.describe(**address),
+                stats.calls
+            ));
+        }
+        report
+    }
+
+    //Input for flamegraph.pl and inferno, one line per call stack with its own cycles
+    pub fn folded_stacks(&self) -> String {
+        let mut lines: Vec<String> = self
+            .folded
+            .iter()
+            .map(|(stack, count)| {
+                let mut frames = vec!["main".to_string()];
+                frames.extend(stack.iter().map(|&address| {
+                    self.symbols
+                        .resolve(address)
+                        .unwrap_or_else(|| format!("sub_{:03X}", address))
+                }));
+                format!("{} {}", frames.join(";"), count)
+            })
+            .collect();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    //Every instruction of the ROM and every other executed address with how often it ran
+    pub fn annotated_listing(&self) -> String {
+        let mut addresses: BTreeSet<u16> = (0..self.rom.len() / 2)
+            .map(|i| self.load_address + i as u16 * 2)
+            .collect();
+        addresses.extend(self.pc_counts.keys());
+
+        let mut listing = String::new();
+        for address in addresses {
+            let in_rom = (address as usize)
+                .checked_sub(self.load_address as usize)
+                .filter(|&offset| offset + 1 < self.rom.len())
+                .map(|offset| (self.rom[offset] as u16) << 8 | self.rom[offset + 1] as u16);
+            let opcode = match self.opcodes.get(&address).copied().or(in_rom) {
+                Some(opcode) => opcode,
+                None => continue,
+            };
+            if let Some(label) = self.symbols.label_at(address) {
+                listing.push_str(&format!("{}:\n", label));
+            }
+            let count = self.pc_counts.get(&address).copied().unwrap_or(0);
+            let counted = if count > 0 {
+                format!("{:>12} {:>6.2}%", count, self.percent(count))
+            } else {
+                " ".repeat(20)
+            };
+            listing.push_str(&format!(
+                "{}  {:03X}  {:04X}  {}\n",
+                counted,
+                address,
+                opcode,
+                disassemble_with(opcode, &self.symbols)
+            ));
+        }
+        listing
+    }
+
+    pub fn write_reports(&self, rom_name: &str) -> io::Result<()> {
+        let base = format!("{}_profile", rom_name);
+        fs::write(format!("{}.txt", base), self.report())?;
+        fs::write(format!("{}.folded", base), self.folded_stacks())?;
+        fs::write(format!("{}.asm", base), self.annotated_listing())
+    }
+}
+
+//Installed as tracer of the chip, the frontend keeps the other handle to write the reports
+pub struct Profiler(pub Arc<Mutex<Profile>>);
+
+impl Tracer for Profiler {
+    fn trace(&mut self, entry: &TraceEntry) {
+        self.0.lock().unwrap().record(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Profile, Profiler};
+    use crate::chip8::{Chip8, Symbols};
+    use std::sync::{Arc, Mutex};
+
+    //Calls draw twice, which calls plot, then loops forever
+    const NESTED_CALLS: [u8; 14] = [
+        0x22, 0x06, 0x22, 0x06, 0x12, 0x04, //main
+        0x22, 0x0A, 0x00, 0xEE, //draw
+        0x60, 0x01, 0x00, 0xEE, //plot
+    ];
+
+    #[test]
+    fn test_folded_stacks() {
+        let mut symbols = Symbols::default();
+        symbols.insert("plot", 0x20A);
+        let profile = Arc::new(Mutex::new(Profile::new(
+            NESTED_CALLS.to_vec(),
+            0x200,
+            symbols,
+        )));
+        let mut chip = Chip8::from_rom(NESTED_CALLS.to_vec()).unwrap();
+        chip.tracer = Some(Box::new(Profiler(profile.clone())));
+        for _ in 0..11 {
+            chip.emulate_cycle().unwrap();
+        }
+
+        let profile = profile.lock().unwrap();
+        assert_eq!(
+            profile.folded_stacks(),
+            "main 3\nmain;sub_206 4\nmain;sub_206;plot 4\n"
+        );
+        assert!(profile.report().starts_with("11 cycles\n"));
+        assert!(profile.report().contains("2 calls"));
+    }
+}
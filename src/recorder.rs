@@ -0,0 +1,278 @@
+use crate::filter::Gfx;
+use crate::palette::Palette;
+use crate::screenshot;
+use crate::Options;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+const FRAMES_PER_SECOND: u64 = 60;
+const SAMPLE_RATE: u32 = 44_100;
+const BEEP_FREQUENCY: u32 = 440;
+
+fn io_error<E: Into<Box<dyn Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::other(error)
+}
+
+//Records gfx at 60Hz into a two colour GIF, repeated frames are merged into one longer frame.
+//The GIF has the resolution of the first frame, frames in the other resolution get scaled to it.
+pub struct GifRecorder {
+    path: PathBuf,
+    colours: Vec<u8>,
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+    size: (usize, usize),
+    pending: Option<Gfx>,
+    frames: u64,
+    centiseconds_written: u64,
+}
+
+impl GifRecorder {
+    pub fn create(path: &Path, palette: &Palette) -> io::Result<GifRecorder> {
+        let mut colours = palette.background.to_vec();
+        colours.extend_from_slice(&palette.foreground);
+        //Fail now rather than at the first frame
+        File::create(path)?;
+
+        Ok(GifRecorder {
+            path: path.to_path_buf(),
+            colours,
+            encoder: None,
+            size: (0, 0),
+            pending: None,
+            frames: 0,
+            centiseconds_written: 0,
+        })
+    }
+
+    pub fn add_frame(&mut self, gfx: &Gfx) -> io::Result<()> {
+        if self.pending.as_ref() != Some(gfx) {
+            self.flush()?;
+            self.pending = Some(*gfx);
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    //GIF delays are in 1/100s, so the delay is rounded against the total time to avoid drifting
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(gfx) = self.pending.take() {
+            let end = (self.frames * 100 + FRAMES_PER_SECOND / 2) / FRAMES_PER_SECOND;
+            let delay = end.saturating_sub(self.centiseconds_written).max(1);
+            self.centiseconds_written += delay;
+
+            if self.encoder.is_none() {
+                self.size = (gfx.width(), gfx.height());
+            }
+            let (width, height) = self.size;
+            let indices: Vec<u8> = (0..height)
+                .flat_map(|y| {
+                    (0..width).map(move |x| scaled_pixel(&gfx, x, y, width, height) as u8)
+                })
+                .collect();
+            let frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: delay as u16,
+                buffer: Cow::Owned(indices),
+                ..gif::Frame::default()
+            };
+
+            let encoder = match self.encoder.as_mut() {
+                Some(encoder) => encoder,
+                None => {
+                    let mut encoder = gif::Encoder::new(
+                        BufWriter::new(File::create(&self.path)?),
+                        width as u16,
+                        height as u16,
+                        &self.colours,
+                    )
+                    .map_err(io_error)?;
+                    encoder
+                        .set_repeat(gif::Repeat::Infinite)
+                        .map_err(io_error)?;
+                    self.encoder.insert(encoder)
+                }
+            };
+            encoder.write_frame(&frame).map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+//A pixel of gfx scaled to width x height, lit if any pixel it covers is
+fn scaled_pixel(gfx: &Gfx, x: usize, y: usize, width: usize, height: usize) -> bool {
+    let source = |position: usize, size: usize, source_size: usize| {
+        let start = position * source_size / size;
+        start..((position + 1) * source_size / size).max(start + 1)
+    };
+    let columns = source(x, width, gfx.width());
+    source(y, height, gfx.height()).any(|y| columns.clone().any(|x| gfx[y][x]))
+}
+
+//Dumps every frame as a numbered PNG next to a WAV of the beeper, for muxing with external tools
+pub struct FrameDumper {
+    directory: PathBuf,
+    palette: Palette,
+    frame: u64,
+    wav: hound::WavWriter<BufWriter<File>>,
+    sample: u64,
+}
+
+impl FrameDumper {
+    pub fn create(directory: &Path, palette: &Palette) -> io::Result<FrameDumper> {
+        fs::create_dir_all(directory)?;
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let wav = hound::WavWriter::create(directory.join("beeper.wav"), spec).map_err(io_error)?;
+
+        Ok(FrameDumper {
+            directory: directory.to_path_buf(),
+            palette: *palette,
+            frame: 0,
+            wav,
+            sample: 0,
+        })
+    }
+
+    pub fn add_frame(&mut self, gfx: &Gfx, sound: bool) -> io::Result<()> {
+        let path = self.directory.join(format!("frame_{:06}.png", self.frame));
+        screenshot::save_png(&path, gfx, &self.palette, 1)?;
+        self.frame += 1;
+
+        //Square wave while the sound timer is running, silence otherwise
+        let end = self.frame * SAMPLE_RATE as u64 / FRAMES_PER_SECOND;
+        let half_period = (SAMPLE_RATE / BEEP_FREQUENCY / 2) as u64;
+        while self.sample < end {
+            let level = if !sound {
+                0
+            } else if (self.sample / half_period).is_multiple_of(2) {
+                i16::MAX / 4
+            } else {
+                -i16::MAX / 4
+            };
+            self.wav.write_sample(level).map_err(io_error)?;
+            self.sample += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.wav.finalize().map_err(io_error)
+    }
+}
+
+//Everything that is being recorded right now, shared by the frontends and the headless runner.
+//Messages go to stderr, or are kept for a status line when stdout shows the screen.
+pub struct Recording {
+    gif: Option<GifRecorder>,
+    frame_dump: Option<FrameDumper>,
+    status: Option<Vec<String>>,
+}
+
+impl Recording {
+    pub fn start(options: &Options) -> Recording {
+        Recording::start_with(options, None)
+    }
+
+    pub fn start_with_status_line(options: &Options) -> Recording {
+        Recording::start_with(options, Some(Vec::new()))
+    }
+
+    fn start_with(options: &Options, status: Option<Vec<String>>) -> Recording {
+        let mut recording = Recording {
+            gif: None,
+            frame_dump: None,
+            status,
+        };
+        if let Some(path) = &options.record {
+            recording.start_gif(path, &options.palette);
+        }
+        if let Some(directory) = &options.dump_frames {
+            match FrameDumper::create(directory, &options.palette) {
+                Ok(dumper) => recording.frame_dump = Some(dumper),
+                Err(e) => recording.report(format!(
+                    "Could not dump frames to {}: {}",
+                    directory.display(),
+                    e
+                )),
+            }
+        }
+        recording
+    }
+
+    fn report(&mut self, message: String) {
+        match &mut self.status {
+            Some(messages) => messages.push(message),
+            None => eprintln!("{}", message),
+        }
+    }
+
+    pub fn take_status(&mut self) -> Vec<String> {
+        self.status.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn start_gif(&mut self, path: &Path, palette: &Palette) {
+        match GifRecorder::create(path, palette) {
+            Ok(gif) => {
+                self.report(format!("Recording to {}", path.display()));
+                self.gif = Some(gif);
+            }
+            Err(e) => self.report(format!("Could not record to {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn toggle_gif(&mut self, options: &Options, frame: u64) {
+        match self.gif.take() {
+            Some(gif) => {
+                let message = finish(gif.finish(), "GIF recording");
+                self.report(message);
+            }
+            None => {
+                let path = PathBuf::from(format!("{}_frame{}.gif", options.rom_name, frame));
+                self.start_gif(&path, &options.palette);
+            }
+        }
+    }
+
+    pub fn add_frame(&mut self, gfx: &Gfx, sound: bool) {
+        if let Some(gif) = &mut self.gif {
+            if let Err(e) = gif.add_frame(gfx) {
+                self.report(format!("Stopped GIF recording: {}", e));
+                self.gif = None;
+            }
+        }
+        if let Some(dumper) = &mut self.frame_dump {
+            if let Err(e) = dumper.add_frame(gfx, sound) {
+                self.report(format!("Stopped frame dump: {}", e));
+                self.frame_dump = None;
+            }
+        }
+    }
+
+    //Called once the screen is given back, so this always goes to stderr
+    pub fn finish(self) {
+        if let Some(gif) = self.gif {
+            eprintln!("{}", finish(gif.finish(), "GIF recording"));
+        }
+        if let Some(dumper) = self.frame_dump {
+            eprintln!("{}", finish(dumper.finish(), "frame dump"));
+        }
+    }
+}
+
+fn finish(result: io::Result<()>, what: &str) -> String {
+    match result {
+        Ok(()) => format!("Finished {}", what),
+        Err(e) => format!("Could not finish {}: {}", what, e),
+    }
+}
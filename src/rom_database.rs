@@ -0,0 +1,329 @@
+use crate::chip8::Quirks;
+use crate::palette::{parse_hex_colour, Palette};
+use clap::ArgMatches;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+//Same layout as the community chip-8-database, data/update.sh fetches it for bundling and a
+//newer checkout can be used with --rom-db
+const BUNDLED_HASHES: &str = include_str!("../data/sha1-hashes.json");
+const BUNDLED_PROGRAMS: &str = include_str!("../data/programs.json");
+
+pub struct RomDatabase {
+    hashes: Value,
+    programs: Value,
+}
+
+//What the database knows about one ROM
+pub struct RomInfo {
+    pub title: String,
+    pub platform: Option<String>,
+    pub quirks: Option<Quirks>,
+    //Quirks the ROM needs that this emulator doesn't have
+    pub unsupported_quirks: Vec<String>,
+    pub tickrate: Option<u32>,
+    pub palette: Option<Palette>,
+    pub keys: Vec<(String, u8)>,
+}
+
+//The settings the database can choose for a ROM
+pub struct RomConfig {
+    pub quirks: Quirks,
+    pub speed: u32,
+    pub palette: Palette,
+}
+
+impl RomInfo {
+    //Settings given on the command line win over the database, what the database changed is
+    //returned so the settings chosen in the launcher aren't replaced silently
+    pub fn configure(&self, config: &mut RomConfig, matches: &ArgMatches) -> Vec<String> {
+        let mut changes = Vec::new();
+        if let (Some(quirks), false) = (self.quirks, matches.is_present("quirks")) {
+            if quirks != config.quirks {
+                changes.push(format!(
+                    "Using the quirks of {} from the ROM database, --quirks overrides them",
+                    self.platform.as_deref().unwrap_or("the ROM")
+                ));
+            }
+            config.quirks = quirks;
+        }
+        if let (Some(tickrate), false) = (self.tickrate, matches.is_present("speed")) {
+            if tickrate * 60 != config.speed {
+                changes.push(format!(
+                    "Using speed {} from the ROM database, --speed overrides it",
+                    tickrate * 60
+                ));
+            }
+            config.speed = tickrate * 60;
+        }
+        if let (Some(palette), false) = (self.palette, matches.is_present("palette")) {
+            if palette != config.palette {
+                changes.push(
+                    "Using the colours from the ROM database, --palette overrides them".to_string(),
+                );
+            }
+            config.palette = palette;
+        }
+        changes
+    }
+}
+
+impl RomDatabase {
+    pub fn bundled() -> RomDatabase {
+        RomDatabase::parse(BUNDLED_HASHES, BUNDLED_PROGRAMS).unwrap()
+    }
+
+    pub fn load(directory: &Path) -> io::Result<RomDatabase> {
+        let hashes = fs::read_to_string(directory.join("sha1-hashes.json"))?;
+        let programs = fs::read_to_string(directory.join("programs.json"))?;
+        RomDatabase::parse(&hashes, &programs).map_err(io::Error::other)
+    }
+
+    fn parse(hashes: &str, programs: &str) -> serde_json::Result<RomDatabase> {
+        Ok(RomDatabase {
+            hashes: serde_json::from_str(hashes)?,
+            programs: serde_json::from_str(programs)?,
+        })
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<RomInfo> {
+        let index = self.hashes.get(hash)?.as_u64()? as usize;
+        let program = self.programs.get(index)?;
+        let rom = program.get("roms")?.get(hash)?;
+
+        //The first platform we can emulate wins, ROM specific quirks are applied on top of it
+        let platform = rom
+            .get("platforms")
+            .and_then(Value::as_array)
+            .and_then(|platforms| {
+                platforms
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|p| platform_quirks(p).is_some())
+            });
+        let mut unsupported_quirks = Vec::new();
+        let quirks = platform.and_then(platform_quirks).map(|mut quirks| {
+            if let Some(overrides) = rom
+                .get("quirkyPlatforms")
+                .and_then(|q| q.get(platform.unwrap()))
+            {
+                unsupported_quirks = apply_quirk_overrides(&mut quirks, overrides);
+            }
+            quirks
+        });
+
+        let palette = rom
+            .get("colors")
+            .and_then(|c| c.get("pixels"))
+            .and_then(Value::as_array)
+            .and_then(|pixels| {
+                let background = parse_hex_colour(pixels.first()?.as_str()?)?;
+                let foreground = parse_hex_colour(pixels.get(1)?.as_str()?)?;
+                Some(Palette {
+                    foreground,
+                    background,
+                })
+            });
+
+        let keys = rom
+            .get("keys")
+            .and_then(Value::as_object)
+            .map_or(Vec::new(), |keys| {
+                keys.iter()
+                    .filter_map(|(action, key)| Some((action.clone(), key.as_u64()? as u8)))
+                    .collect()
+            });
+
+        Some(RomInfo {
+            title: program
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown")
+                .to_string(),
+            platform: platform.map(str::to_string),
+            quirks,
+            unsupported_quirks,
+            tickrate: rom
+                .get("tickrate")
+                .and_then(Value::as_u64)
+                .map(|t| t as u32),
+            palette,
+            keys,
+        })
+    }
+}
+
+fn platform_quirks(platform: &str) -> Option<Quirks> {
+    match platform {
+        "originalChip8" | "hybridVIP" | "chip8x" => Some(Quirks::CHIP8),
+        "modernChip8" => Some(Quirks::DEFAULT),
+        "chip48" | "superchip1" | "superchip" => Some(Quirks::SCHIP),
+        "xochip" => Some(Quirks::XOCHIP),
+        _ => None,
+    }
+}
+
+//In the database a quirk being true means the behaviour that differs from the original VIP.
+//Returns the quirks that are set but not emulated.
+fn apply_quirk_overrides(quirks: &mut Quirks, overrides: &Value) -> Vec<String> {
+    let quirk = |name: &str| overrides.get(name).and_then(Value::as_bool);
+
+    if let Some(shift) = quirk("shift") {
+        quirks.shift_uses_vy = !shift;
+    }
+    if let Some(leave_i) = quirk("memoryLeaveIUnchanged") {
+        quirks.load_store_increments_i = !leave_i;
+    }
+    if let Some(wrap) = quirk("wrap") {
+        quirks.clip_sprites = !wrap;
+    }
+    if let Some(jump) = quirk("jump") {
+        quirks.jump_uses_vx = jump;
+    }
+    if let Some(logic) = quirk("logic") {
+        quirks.logic_resets_vf = logic;
+    }
+    //Unlike the others vblank is true on the VIP
+    if let Some(vblank) = quirk("vblank") {
+        quirks.display_wait = vblank;
+    }
+    //SCHIP 1.0 adds only X to I in FX55 and FX65, there is no quirk for that
+    let mut unsupported = Vec::new();
+    if quirk("memoryIncrementByX") == Some(true) {
+        unsupported.push("memoryIncrementByX".to_string());
+    }
+    unsupported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RomConfig, RomDatabase};
+    use crate::chip8::Quirks;
+    use crate::palette::Palette;
+    use clap::{App, Arg};
+    use std::path::Path;
+
+    const QUIRKY: &str = "1111111111111111111111111111111111111111";
+    const PLAIN: &str = "2222222222222222222222222222222222222222";
+    const UNKNOWN_PLATFORM: &str = "3333333333333333333333333333333333333333";
+    const INCREMENT_BY_X: &str = "4444444444444444444444444444444444444444";
+
+    fn fixture() -> RomDatabase {
+        RomDatabase::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/rom_db")).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_database() {
+        RomDatabase::bundled();
+    }
+
+    #[test]
+    fn test_lookup_unknown_hash() {
+        assert!(fixture()
+            .lookup("0000000000000000000000000000000000000000")
+            .is_none());
+    }
+
+    //Every quirk in the fixture is set to the opposite of the original VIP
+    #[test]
+    fn test_lookup_quirks() {
+        let info = fixture().lookup(QUIRKY).unwrap();
+        assert_eq!(info.title, "Quirky Fixture");
+        assert_eq!(info.platform.as_deref(), Some("originalChip8"));
+        assert_eq!(
+            info.quirks,
+            Some(Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                clip_sprites: false,
+                display_wait: false,
+            })
+        );
+        assert!(info.unsupported_quirks.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_unsupported_quirk() {
+        let info = fixture().lookup(INCREMENT_BY_X).unwrap();
+        assert_eq!(info.platform.as_deref(), Some("superchip1"));
+        assert_eq!(info.quirks, Some(Quirks::SCHIP));
+        assert_eq!(info.unsupported_quirks, vec!["memoryIncrementByX"]);
+    }
+
+    #[test]
+    fn test_lookup_tickrate_colours_keys() {
+        let info = fixture().lookup(QUIRKY).unwrap();
+        assert_eq!(info.tickrate, Some(15));
+        assert_eq!(
+            info.palette,
+            Some(Palette {
+                foreground: [0xF0, 0xE0, 0xD0],
+                background: [0x10, 0x20, 0x30],
+            })
+        );
+        assert_eq!(
+            info.keys,
+            vec![("down".to_string(), 8), ("up".to_string(), 5)]
+        );
+    }
+
+    #[test]
+    fn test_lookup_platform() {
+        let database = fixture();
+        let info = database.lookup(PLAIN).unwrap();
+        assert_eq!(info.title, "Plain Fixture");
+        assert_eq!(info.platform.as_deref(), Some("superchip"));
+        assert_eq!(info.quirks, Some(Quirks::SCHIP));
+        assert_eq!(info.tickrate, None);
+        assert_eq!(info.palette, None);
+        assert!(info.keys.is_empty());
+
+        let info = database.lookup(UNKNOWN_PLATFORM).unwrap();
+        assert_eq!(info.platform, None);
+        assert_eq!(info.quirks, None);
+    }
+
+    #[test]
+    fn test_configure_keeps_command_line_settings() {
+        let app = App::new("test").args(&[
+            Arg::with_name("quirks").long("quirks").takes_value(true),
+            Arg::with_name("speed").long("speed").takes_value(true),
+            Arg::with_name("palette").long("palette").takes_value(true),
+        ]);
+        let info = fixture().lookup(QUIRKY).unwrap();
+        let config = || RomConfig {
+            quirks: Quirks::DEFAULT,
+            speed: 500,
+            palette: Palette::default(),
+        };
+
+        let mut from_database = config();
+        let changes = info.configure(
+            &mut from_database,
+            &app.clone().get_matches_from(vec!["test"]),
+        );
+        assert_eq!(changes.len(), 3);
+        assert_eq!(from_database.quirks, info.quirks.unwrap());
+        assert_eq!(from_database.speed, 15 * 60);
+        assert_eq!(from_database.palette, info.palette.unwrap());
+
+        let mut from_command_line = config();
+        let matches = app.get_matches_from(vec![
+            "test",
+            "--quirks",
+            "default",
+            "--speed",
+            "500",
+            "--palette",
+            "white",
+        ]);
+        assert!(info.configure(&mut from_command_line, &matches).is_empty());
+        assert_eq!(from_command_line.quirks, Quirks::DEFAULT);
+        assert_eq!(from_command_line.speed, 500);
+        assert_eq!(from_command_line.palette, Palette::default());
+    }
+}
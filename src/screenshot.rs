@@ -0,0 +1,42 @@
+use crate::filter::Gfx;
+use crate::palette::Palette;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+pub fn file_name(rom_name: &str, frame: u64) -> PathBuf {
+    PathBuf::from(format!("{}_frame{}.png", rom_name, frame))
+}
+
+//Writes gfx as RGB PNG, every chip pixel becomes a scale*scale block
+pub fn save_png(path: &Path, gfx: &Gfx, palette: &Palette, scale: u32) -> io::Result<()> {
+    let scale = scale.max(1) as usize;
+    let width = gfx.width() * scale;
+    let height = gfx.height() * scale;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for row in gfx.rows() {
+        let mut line = Vec::with_capacity(width * 3);
+        for &pixel in row.iter() {
+            let colour = palette.colour(if pixel { 255 } else { 0 });
+            for _ in 0..scale {
+                line.extend_from_slice(&colour);
+            }
+        }
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+
+    Ok(())
+}
@@ -0,0 +1,469 @@
+use crate::chip8::{Chip8, Core, Registers};
+use crate::palette::Palette;
+use crate::screenshot;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, AST};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+//Callbacks the script registered with on_frame, on_pc and on_write
+#[derive(Default)]
+struct Hooks {
+    frame: Vec<FnPtr>,
+    pc: HashMap<u16, Vec<FnPtr>>,
+    write: Vec<(RangeInclusive<u16>, FnPtr)>,
+}
+
+//The chip as the script sees it while it runs, changes are copied back to the chip afterwards
+struct Context {
+    registers: Registers,
+    memory: Vec<u8>,
+    registers_changed: bool,
+    writes: Vec<(u16, u8)>,
+    keys: [bool; 16],
+    screenshots: Vec<Option<PathBuf>>,
+    failures: u32,
+    frame: u64,
+    cycles: u64,
+    quit: bool,
+    hooks: Hooks,
+}
+
+//A Rhai script that tests or plays a ROM, see the functions registered in Script::load
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    context: Arc<Mutex<Context>>,
+    palette: Palette,
+    rom_name: String,
+    //PC hooks fire when the PC arrives, not again while it stays like in FX0A or a stalled DXYN
+    last_pc: Option<u16>,
+}
+
+impl Script {
+    pub fn load(path: &Path, palette: Palette, rom_name: String) -> Result<Script, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read script {}: {}", path.display(), e))?;
+        let context = Arc::new(Mutex::new(Context {
+            registers: Registers {
+                v: [0; 16],
+                i_reg: 0,
+                pc: 0,
+                sp: 0,
+                delay_timer: 0,
+                sound_timer: 0,
+            },
+            memory: vec![0; 4096],
+            registers_changed: false,
+            writes: Vec::new(),
+            keys: [false; 16],
+            screenshots: Vec::new(),
+            failures: 0,
+            frame: 0,
+            cycles: 0,
+            quit: false,
+            hooks: Hooks::default(),
+        }));
+        let mut engine = Engine::new();
+        register_functions(&mut engine, &context);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("Could not compile script {}: {}", path.display(), e))?;
+        Ok(Script {
+            engine,
+            ast,
+            context,
+            palette,
+            rom_name,
+            last_pc: None,
+        })
+    }
+
+    //Runs the top level of the script, which registers the callbacks
+    pub fn start(&mut self, chip: &mut Chip8) {
+        self.sync_in(chip);
+        if let Err(e) = self.engine.run_ast(&self.ast) {
+            self.error(e);
+        }
+        self.sync_out(chip);
+    }
+
+    //Keys the script holds down are pressed in addition to the player's
+    pub fn press_keys(&self, keys: &mut [bool; 16]) {
+        let context = self.context.lock().unwrap();
+        for (key, &pressed) in keys.iter_mut().zip(context.keys.iter()) {
+            *key |= pressed;
+        }
+    }
+
+    //PC and memory write callbacks, PC callbacks run before the instruction at that address
+    pub fn after_cycle(&mut self, chip: &mut Chip8) {
+        let pc = chip.registers().pc;
+        let arrived = self.last_pc.replace(pc) != Some(pc);
+        let calls = {
+            let context = self.context.lock().unwrap();
+            let hooks = &context.hooks;
+            if hooks.pc.is_empty() && hooks.write.is_empty() {
+                return;
+            }
+            let mut calls: Vec<(FnPtr, i64)> = hooks
+                .pc
+                .get(&pc)
+                .filter(|_| arrived)
+                .into_iter()
+                .flatten()
+                .map(|hook| (hook.clone(), pc as i64))
+                .collect();
+            //The callback gets the first written address it watches
+            if let Some(written) = &chip.last_write {
+                for (watched, hook) in hooks.write.iter() {
+                    if let Some(address) = written.clone().find(|a| watched.contains(a)) {
+                        calls.push((hook.clone(), address as i64));
+                    }
+                }
+            }
+            calls
+        };
+        if calls.is_empty() {
+            return;
+        }
+
+        self.sync_in(chip);
+        for (hook, address) in calls {
+            if let Err(e) = hook.call::<Dynamic>(&self.engine, &self.ast, (address,)) {
+                self.error(e);
+            }
+        }
+        self.sync_out(chip);
+    }
+
+    pub fn end_frame(&mut self, chip: &mut Chip8) {
+        let hooks = self.context.lock().unwrap().hooks.frame.clone();
+        if !hooks.is_empty() {
+            self.sync_in(chip);
+            for hook in hooks {
+                if let Err(e) = hook.call::<Dynamic>(&self.engine, &self.ast, ()) {
+                    self.error(e);
+                }
+            }
+            self.sync_out(chip);
+        }
+        self.context.lock().unwrap().frame += 1;
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.context.lock().unwrap().quit
+    }
+
+    //Failed assertions and script errors
+    pub fn failures(&self) -> u32 {
+        self.context.lock().unwrap().failures
+    }
+
+    fn error(&self, e: Box<EvalAltResult>) {
+        println!("Script error: {}", e);
+        self.context.lock().unwrap().failures += 1;
+    }
+
+    fn sync_in(&self, chip: &Chip8) {
+        let mut context = self.context.lock().unwrap();
+        context.registers = chip.registers();
+        context.memory = chip.state().memory.to_vec();
+        context.cycles = chip.cycles;
+    }
+
+    fn sync_out(&self, chip: &mut Chip8) {
+        let mut context = self.context.lock().unwrap();
+        if context.registers_changed {
+            context.registers_changed = false;
+            if let Err(e) = chip.set_registers(&context.registers) {
+                println!("Script error: {}", e);
+                context.failures += 1;
+            }
+        }
+        for (address, value) in mem::take(&mut context.writes) {
+            //The script's copy of memory covers all 4 KiB, the chip may have less
+            if let Err(e) = chip.write_memory(address, &[value]) {
+                println!("Script error: {}", e);
+                context.failures += 1;
+            }
+        }
+        for path in mem::take(&mut context.screenshots) {
+            let path = path.unwrap_or_else(|| screenshot::file_name(&self.rom_name, context.frame));
+            match screenshot::save_png(&path, &chip.gfx, &self.palette, 1) {
+                Ok(()) => println!("Saved screenshot {}", path.display()),
+                Err(e) => println!("Could not save screenshot {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+fn register_functions(engine: &mut Engine, context: &Arc<Mutex<Context>>) {
+    let c = context.clone();
+    engine.register_fn("on_frame", move |hook: FnPtr| {
+        c.lock().unwrap().hooks.frame.push(hook);
+    });
+    let c = context.clone();
+    engine.register_fn(
+        "on_pc",
+        move |address: i64, hook: FnPtr| -> ScriptResult<()> {
+            let address = to_address(address)?;
+            let mut context = c.lock().unwrap();
+            context.hooks.pc.entry(address).or_default().push(hook);
+            Ok(())
+        },
+    );
+    let c = context.clone();
+    engine.register_fn(
+        "on_write",
+        move |address: i64, hook: FnPtr| -> ScriptResult<()> {
+            let address = to_address(address)?;
+            c.lock()
+                .unwrap()
+                .hooks
+                .write
+                .push((address..=address, hook));
+            Ok(())
+        },
+    );
+    //Both ends included like --trace-range
+    let c = context.clone();
+    engine.register_fn(
+        "on_write",
+        move |start: i64, end: i64, hook: FnPtr| -> ScriptResult<()> {
+            let range = to_address(start)?..=to_address(end)?;
+            c.lock().unwrap().hooks.write.push((range, hook));
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("reg", move |name: &str| -> ScriptResult<i64> {
+        let context = c.lock().unwrap();
+        let registers = &context.registers;
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "i" => registers.i_reg as i64,
+            "pc" => registers.pc as i64,
+            "sp" => registers.sp as i64,
+            "dt" => registers.delay_timer as i64,
+            "st" => registers.sound_timer as i64,
+            name => registers.v[v_index(name)?] as i64,
+        })
+    });
+    let c = context.clone();
+    engine.register_fn(
+        "set_reg",
+        move |name: &str, value: i64| -> ScriptResult<()> {
+            let mut context = c.lock().unwrap();
+            let registers = &mut context.registers;
+            match name.to_ascii_lowercase().as_str() {
+                "i" => registers.i_reg = to_address(value)?,
+                "pc" => registers.pc = to_address(value)?,
+                "sp" => registers.sp = to_byte(value)?,
+                "dt" => registers.delay_timer = to_byte(value)?,
+                "st" => registers.sound_timer = to_byte(value)?,
+                name => registers.v[v_index(name)?] = to_byte(value)?,
+            }
+            context.registers_changed = true;
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("peek", move |address: i64| -> ScriptResult<i64> {
+        let address = to_address(address)?;
+        Ok(c.lock().unwrap().memory[address as usize % 4096] as i64)
+    });
+    let c = context.clone();
+    engine.register_fn(
+        "poke",
+        move |address: i64, value: i64| -> ScriptResult<()> {
+            let address = to_address(address)?;
+            let value = to_byte(value)?;
+            let mut context = c.lock().unwrap();
+            context.memory[address as usize % 4096] = value;
+            context.writes.push((address, value));
+            Ok(())
+        },
+    );
+
+    let c = context.clone();
+    engine.register_fn("press", move |key: i64| -> ScriptResult<()> {
+        c.lock().unwrap().keys[key_index(key)?] = true;
+        Ok(())
+    });
+    let c = context.clone();
+    engine.register_fn("release", move |key: i64| -> ScriptResult<()> {
+        c.lock().unwrap().keys[key_index(key)?] = false;
+        Ok(())
+    });
+
+    let c = context.clone();
+    engine.register_fn("screenshot", move || {
+        c.lock().unwrap().screenshots.push(None);
+    });
+    let c = context.clone();
+    engine.register_fn("screenshot", move |path: &str| {
+        c.lock()
+            .unwrap()
+            .screenshots
+            .push(Some(PathBuf::from(path)));
+    });
+
+    //A failed assertion is reported and counted, the script keeps running
+    let c = context.clone();
+    engine.register_fn("assert", move |condition: bool| {
+        assert(&c, condition, "");
+    });
+    let c = context.clone();
+    engine.register_fn("assert", move |condition: bool, message: &str| {
+        assert(&c, condition, message);
+    });
+
+    let c = context.clone();
+    engine.register_fn("frame", move || c.lock().unwrap().frame as i64);
+    let c = context.clone();
+    engine.register_fn("cycles", move || c.lock().unwrap().cycles as i64);
+    let c = context.clone();
+    engine.register_fn("quit", move || {
+        c.lock().unwrap().quit = true;
+    });
+}
+
+fn assert(context: &Mutex<Context>, condition: bool, message: &str) {
+    if condition {
+        return;
+    }
+    let mut context = context.lock().unwrap();
+    context.failures += 1;
+    println!(
+        "Assertion failed in frame {} at PC {:03X}: {}",
+        context.frame, context.registers.pc, message
+    );
+}
+
+fn to_address(value: i64) -> ScriptResult<u16> {
+    u16::try_from(value).map_err(|_| format!("{} is not an address", value).into())
+}
+
+fn to_byte(value: i64) -> ScriptResult<u8> {
+    u8::try_from(value).map_err(|_| format!("{} doesn't fit into a byte", value).into())
+}
+
+fn key_index(key: i64) -> ScriptResult<usize> {
+    match key {
+        0..=15 => Ok(key as usize),
+        _ => Err(format!("there is no key {}", key).into()),
+    }
+}
+
+//v0 to vf
+fn v_index(name: &str) -> ScriptResult<usize> {
+    name.strip_prefix('v')
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| usize::from_str_radix(digit, 16).ok())
+        .ok_or_else(|| format!("there is no register {}", name).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Script;
+    use crate::chip8::{Chip8, Machine};
+    use crate::palette::Palette;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn script(name: &str, source: &str) -> Script {
+        let path = env::temp_dir().join(format!("chip8_script_{}_{}.rhai", process::id(), name));
+        fs::write(&path, source).unwrap();
+        let script = Script::load(&path, Palette::default(), name.to_string()).unwrap();
+        fs::remove_file(&path).unwrap();
+        script
+    }
+
+    #[test]
+    fn test_registers_and_keys() {
+        let mut chip = Chip8::from_rom(vec![0x12, 0x00]).unwrap();
+        let mut script = script(
+            "registers",
+            r#"
+                set_reg("v3", 7);
+                set_reg("I", 0x300);
+                poke(0x300, reg("v3") + 1);
+                press(5);
+                press(6);
+                release(6);
+            "#,
+        );
+        script.start(&mut chip);
+
+        assert_eq!(chip.registers().v[3], 7);
+        assert_eq!(chip.registers().i_reg, 0x300);
+        assert_eq!(chip.read_memory(0x300, 1).unwrap(), &[8]);
+        let mut keys = [false; 16];
+        keys[1] = true;
+        script.press_keys(&mut keys);
+        assert_eq!(keys.iter().filter(|&&key| key).count(), 2);
+        assert!(keys[1] && keys[5]);
+        assert_eq!(script.failures(), 0);
+    }
+
+    //LD I, 300; LD V0, 42; LD [I], V0 and then a jump to itself
+    #[test]
+    fn test_pc_and_write_hooks() {
+        let mut chip =
+            Chip8::from_rom(vec![0xA3, 0x00, 0x60, 0x42, 0xF0, 0x55, 0x12, 0x06]).unwrap();
+        let mut script = script(
+            "hooks",
+            r#"
+                fn arrived(address) {
+                    poke(0x310, peek(0x310) + 1);
+                }
+                fn written(address) {
+                    set_reg("v1", address - 0x300 + peek(0x300));
+                }
+                on_pc(0x206, Fn("arrived"));
+                on_write(0x300, 0x30F, Fn("written"));
+            "#,
+        );
+        script.start(&mut chip);
+        for _ in 0..10 {
+            chip.emulate_cycle().unwrap();
+            script.after_cycle(&mut chip);
+        }
+
+        assert_eq!(chip.read_memory(0x310, 1).unwrap(), &[1]);
+        assert_eq!(chip.registers().v[1], 0x42);
+        assert_eq!(script.failures(), 0);
+    }
+
+    #[test]
+    fn test_failures() {
+        let machine = Machine::from_name("vip2k").unwrap();
+        let mut chip = Chip8::with_machine(machine, vec![0x12, 0x00]).unwrap();
+        let mut script = script(
+            "failures",
+            r#"
+                assert(true);
+                assert(false, "counted");
+                poke(0x900, 1);
+                on_frame(|| quit());
+                set_reg("vg", 1);
+            "#,
+        );
+        script.start(&mut chip);
+        //The assertion, the error that stopped the script and the write beyond RAM
+        assert_eq!(script.failures(), 3);
+
+        //Hooks registered before the error still run
+        script.end_frame(&mut chip);
+        assert!(script.quit_requested());
+    }
+}
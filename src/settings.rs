@@ -0,0 +1,86 @@
+use crate::chip8::Scheduler;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+//Choices made in the launcher, remembered between runs as the launcher's defaults
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub rom_directory: PathBuf,
+    pub quirks: String,
+    pub speed: u32,
+    pub scale: u32,
+    pub palette: String,
+    pub keymap: String,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            rom_directory: PathBuf::from("."),
+            quirks: "default".to_string(),
+            speed: Scheduler::DEFAULT_SPEED,
+            scale: 10,
+            palette: "white".to_string(),
+            keymap: "qwertz".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    fn home() -> PathBuf {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map_or(PathBuf::from("."), PathBuf::from)
+    }
+
+    fn path() -> PathBuf {
+        Settings::home().join(".chip8_emulator")
+    }
+
+    //Holds a file of cheats per ROM, named after its hash
+    pub fn cheat_directory() -> PathBuf {
+        Settings::home().join(".chip8_emulator_cheats")
+    }
+
+    //Missing or broken entries keep their default value
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let content = match fs::read_to_string(Settings::path()) {
+            Ok(content) => content,
+            Err(_) => return settings,
+        };
+
+        for line in content.lines() {
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => continue,
+            };
+            match key {
+                "rom_directory" => settings.rom_directory = PathBuf::from(value),
+                "quirks" => settings.quirks = value.to_string(),
+                "speed" => settings.speed = value.parse().unwrap_or(settings.speed),
+                "scale" => settings.scale = value.parse().unwrap_or(settings.scale),
+                "palette" => settings.palette = value.to_string(),
+                "keymap" => settings.keymap = value.to_string(),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let content = format!(
+            "rom_directory = {}\nquirks = {}\nspeed = {}\nscale = {}\npalette = {}\nkeymap = {}\n",
+            self.rom_directory.display(),
+            self.quirks,
+            self.speed,
+            self.scale,
+            self.palette,
+            self.keymap
+        );
+        fs::write(Settings::path(), content)
+    }
+}
@@ -0,0 +1,258 @@
+use crate::chip8::{Font, SpriteDraw};
+use crate::text::{draw_text, CELL_HEIGHT};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+const GRID_COLUMNS: u32 = 4;
+const GRID_ROWS: u32 = 4;
+const INFO_LINES: u32 = 5;
+
+const PIXEL: Color = Color {
+    r: 230,
+    g: 230,
+    b: 230,
+    a: 255,
+};
+const SPRITE_BACKGROUND: Color = Color {
+    r: 48,
+    g: 48,
+    b: 60,
+    a: 255,
+};
+const LAST_DRAWN: Color = Color {
+    r: 240,
+    g: 200,
+    b: 80,
+    a: 255,
+};
+const SELECTED: Color = Color {
+    r: 90,
+    g: 160,
+    b: 240,
+    a: 255,
+};
+const TEXT: Color = Color {
+    r: 200,
+    g: 200,
+    b: 200,
+    a: 255,
+};
+
+//Shows memory as a grid of zoomed in 8xN or SCHIP 16x16 sprites
+pub struct SpriteViewer {
+    start: u16,
+    height: u8,
+    wide: bool,
+    selected: Option<u16>,
+}
+
+impl SpriteViewer {
+    pub fn new() -> SpriteViewer {
+        SpriteViewer {
+            start: 0,
+            height: Font::SMALL_HEIGHT as u8,
+            wide: false,
+            selected: None,
+        }
+    }
+
+    fn sprite_size(&self) -> (u32, u32) {
+        if self.wide {
+            (16, 16)
+        } else {
+            (8, self.height as u32)
+        }
+    }
+
+    fn sprite_bytes(&self) -> u16 {
+        if self.wide {
+            32
+        } else {
+            self.height as u16
+        }
+    }
+
+    fn move_start(&mut self, bytes: i32) {
+        self.start = (self.start as i32 + bytes).clamp(0, 4095) as u16;
+    }
+
+    pub fn key_down(
+        &mut self,
+        keycode: Keycode,
+        last_sprite: Option<SpriteDraw>,
+        font_address: u16,
+    ) {
+        let page = (GRID_COLUMNS * GRID_ROWS) as i32 * self.sprite_bytes() as i32;
+        let row = GRID_COLUMNS as i32 * self.sprite_bytes() as i32;
+        match keycode {
+            Keycode::Left => self.move_start(-1),
+            Keycode::Right => self.move_start(1),
+            Keycode::Up => self.move_start(-row),
+            Keycode::Down => self.move_start(row),
+            Keycode::PageUp => self.move_start(-page),
+            Keycode::PageDown => self.move_start(page),
+            Keycode::Plus | Keycode::Equals | Keycode::KpPlus => {
+                self.height = (self.height + 1).min(15)
+            }
+            Keycode::Minus | Keycode::KpMinus => self.height = (self.height - 1).max(1),
+            Keycode::Tab => self.wide = !self.wide,
+            //The small font, the large one follows right after it
+            Keycode::Home => {
+                self.start = font_address;
+                self.height = Font::SMALL_HEIGHT as u8;
+                self.wide = false;
+            }
+            Keycode::End => {
+                if let Some(sprite) = last_sprite {
+                    self.start = sprite.address;
+                    //DXY0 draws a 16x16 sprite on the SCHIP
+                    self.wide = sprite.height == 0;
+                    self.height = sprite.height.max(1);
+                    self.selected = Some(sprite.address);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    //Grid cells and the address of the sprite in each
+    fn cells(&self, area: Rect, scale: u32) -> Vec<(Rect, u16)> {
+        let top = area.y() + (INFO_LINES * CELL_HEIGHT * scale) as i32;
+        let cell_width = area.width() / GRID_COLUMNS;
+        let cell_height = (area.height() as i32 - top + area.y()).max(0) as u32 / GRID_ROWS;
+
+        (0..GRID_COLUMNS * GRID_ROWS)
+            .map(|n| {
+                let cell = Rect::new(
+                    area.x() + (n % GRID_COLUMNS * cell_width) as i32,
+                    top + (n / GRID_COLUMNS * cell_height) as i32,
+                    cell_width.max(1),
+                    cell_height.max(1),
+                );
+                (cell, self.start + n as u16 * self.sprite_bytes())
+            })
+            .filter(|&(_, address)| address < 4096)
+            .collect()
+    }
+
+    pub fn click(&mut self, x: i32, y: i32, area: Rect, scale: u32) {
+        if let Some(&(_, address)) = self
+            .cells(area, scale)
+            .iter()
+            .find(|(cell, _)| cell.contains_point((x, y)))
+        {
+            //Clicking the selected sprite again continues the grid from there
+            if self.selected == Some(address) {
+                self.start = address;
+            }
+            self.selected = Some(address);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        area: Rect,
+        scale: u32,
+        memory: &[u8; 4096],
+        last_sprite: Option<SpriteDraw>,
+    ) {
+        let (sprite_width, sprite_height) = self.sprite_size();
+        let mut info = vec![
+            format!(
+                "SPRITES {}X{} FROM {:04X}",
+                sprite_width, sprite_height, self.start
+            ),
+            match last_sprite {
+                Some(sprite) => format!(
+                    "LAST DXYN I={:04X} N={} AT {},{}",
+                    sprite.address, sprite.height, sprite.x, sprite.y
+                ),
+                None => "NOTHING DRAWN YET".to_string(),
+            },
+        ];
+        if let Some(address) = self.selected {
+            let end = (address as usize + self.sprite_bytes() as usize).min(4096);
+            let bytes: Vec<String> = memory[address as usize..end]
+                .iter()
+                .take(8)
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            info.push(format!("{:04X}: {}", address, bytes.join(" ")));
+        }
+        info.push("ARROWS PGUP PGDN +- TAB HOME END".to_string());
+        for (line, text) in info.iter().enumerate() {
+            let y = area.y() + (line as u32 * CELL_HEIGHT * scale) as i32;
+            draw_text(canvas, text, area.x() + scale as i32, y, scale, TEXT);
+        }
+
+        let label_height = CELL_HEIGHT * scale;
+        for (cell, address) in self.cells(area, scale) {
+            let zoom = (cell.width().saturating_sub(2 * scale) / sprite_width)
+                .min(cell.height().saturating_sub(label_height + 2 * scale) / sprite_height)
+                .max(1);
+            let origin = (cell.x() + scale as i32, cell.y() + scale as i32);
+            let sprite_area = Rect::new(
+                origin.0,
+                origin.1,
+                sprite_width * zoom,
+                sprite_height * zoom,
+            );
+
+            let border = if last_sprite.map(|sprite| sprite.address) == Some(address) {
+                Some(LAST_DRAWN)
+            } else if self.selected == Some(address) {
+                Some(SELECTED)
+            } else {
+                None
+            };
+            if let Some(colour) = border {
+                canvas.set_draw_color(colour);
+                canvas
+                    .fill_rect(Rect::new(
+                        origin.0 - scale as i32,
+                        origin.1 - scale as i32,
+                        sprite_area.width() + 2 * scale,
+                        sprite_area.height() + 2 * scale,
+                    ))
+                    .unwrap();
+            }
+            canvas.set_draw_color(SPRITE_BACKGROUND);
+            canvas.fill_rect(sprite_area).unwrap();
+
+            let mut pixels = Vec::new();
+            let bytes_per_row = sprite_width as usize / 8;
+            for row in 0..sprite_height as usize {
+                for column in 0..sprite_width as usize {
+                    let offset = address as usize + row * bytes_per_row + column / 8;
+                    let set = memory
+                        .get(offset)
+                        .is_some_and(|byte| byte & (0x80 >> (column % 8)) != 0);
+                    if set {
+                        pixels.push(Rect::new(
+                            origin.0 + (column as u32 * zoom) as i32,
+                            origin.1 + (row as u32 * zoom) as i32,
+                            zoom,
+                            zoom,
+                        ));
+                    }
+                }
+            }
+            canvas.set_draw_color(PIXEL);
+            canvas.fill_rects(&pixels).unwrap();
+
+            let label_y = origin.1 + sprite_area.height() as i32 + scale as i32;
+            draw_text(
+                canvas,
+                &format!("{:04X}", address),
+                origin.0,
+                label_y,
+                scale,
+                TEXT,
+            );
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::chip8::{disassemble_with, Symbols, TraceEntry, Tracer};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+//The emulation threads never end on their own, so the buffer gets flushed regularly
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TraceFormat {
+    //One line per instruction: cycle, PC, opcode, mnemonic, V0 to VF and I. With symbols the PC
+    //is followed by its label+offset.
+    Text,
+    //30 bytes little endian per instruction: cycle u64, PC u16, opcode u16, V0 to VF, I u16
+    Binary,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<TraceFormat> {
+        match name {
+            "text" => Some(TraceFormat::Text),
+            "binary" => Some(TraceFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+pub struct TraceWriter {
+    out: BufWriter<Box<dyn Write + Send>>,
+    format: TraceFormat,
+    range: Option<(u16, u16)>,
+    symbols: Symbols,
+    last_flush: Instant,
+    failed: bool,
+}
+
+impl TraceWriter {
+    //Writes to stderr if the target is -
+    pub fn open(
+        target: &str,
+        format: TraceFormat,
+        range: Option<(u16, u16)>,
+        symbols: Symbols,
+    ) -> io::Result<TraceWriter> {
+        let out: Box<dyn Write + Send> = if target == "-" {
+            Box::new(io::stderr())
+        } else {
+            Box::new(File::create(target)?)
+        };
+        Ok(TraceWriter {
+            out: BufWriter::new(out),
+            format,
+            range,
+            symbols,
+            last_flush: Instant::now(),
+            failed: false,
+        })
+    }
+
+    fn write_entry(&mut self, entry: &TraceEntry) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Text => {
+                let registers: Vec<String> = entry.v.iter().map(|v| format!("{:02X}", v)).collect();
+                let location = if self.symbols.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {:<20}", self.symbols.describe(entry.pc))
+                };
+                writeln!(
+                    self.out,
+                    "{:>10} {:03X}{} {:04X} {:<16} {} I={:03X}",
+                    entry.cycle,
+                    entry.pc,
+                    location,
+                    entry.opcode,
+                    disassemble_with(entry.opcode, &self.symbols),
+                    registers.join(" "),
+                    entry.i_reg
+                )?;
+            }
+            TraceFormat::Binary => {
+                self.out.write_all(&entry.cycle.to_le_bytes())?;
+                self.out.write_all(&entry.pc.to_le_bytes())?;
+                self.out.write_all(&entry.opcode.to_le_bytes())?;
+                self.out.write_all(&entry.v)?;
+                self.out.write_all(&entry.i_reg.to_le_bytes())?;
+            }
+        }
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.out.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+impl Tracer for TraceWriter {
+    fn trace(&mut self, entry: &TraceEntry) {
+        let in_range = self
+            .range
+            .is_none_or(|(start, end)| (start..=end).contains(&entry.pc));
+        if self.failed || !in_range {
+            return;
+        }
+        if let Err(e) = self.write_entry(entry) {
+            eprintln!("Stopped tracing: {}", e);
+            self.failed = true;
+        }
+    }
+}
+
+//Hex addresses like 200-2FF, both ends included
+pub fn parse_range(range: &str) -> Option<(u16, u16)> {
+    let mut parts = range.splitn(2, '-');
+    let parse = |part: &str| {
+        let part = part.trim();
+        let part = part.strip_prefix("0x").unwrap_or(part);
+        u16::from_str_radix(part, 16).ok()
+    };
+    let start = parse(parts.next()?)?;
+    let end = parse(parts.next()?)?;
+    Some((start, end))
+}
@@ -0,0 +1,281 @@
+use crate::chip8::Chip8;
+use crate::filter::{DisplayFilter, Intensities};
+use crate::palette::Palette;
+use crate::recorder::Recording;
+use crate::script::Script;
+use crate::watch::RomWatcher;
+use crate::{
+    save_reports, start_logic_thread, stop_logic_thread, take_screenshot, Options, Shared,
+};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{stdout, Write};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+//Terminals only report key presses, so a key counts as held until no repeat arrived for this long.
+//It has to cover the delay before the first repeat, which is usually 250 to 500ms.
+const KEY_HOLD_TIME: Duration = Duration::from_millis(500);
+const FRAME_TIME: Duration = Duration::from_nanos(16_666_666);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Charset {
+    HalfBlock,
+    Braille,
+}
+
+impl Charset {
+    pub fn from_name(name: &str) -> Option<Charset> {
+        match name {
+            "halfblock" => Some(Charset::HalfBlock),
+            "braille" => Some(Charset::Braille),
+            _ => None,
+        }
+    }
+
+    fn pixels_per_row(self) -> usize {
+        match self {
+            Charset::HalfBlock => 2,
+            Charset::Braille => 4,
+        }
+    }
+}
+
+//Raw mode and the alternate screen, left again when dropped, also when the frontend panics
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> TerminalGuard {
+        terminal::enable_raw_mode().unwrap();
+        execute!(stdout(), EnterAlternateScreen, Hide).unwrap();
+        TerminalGuard
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn emulate(
+    chip: Chip8,
+    mut filter: DisplayFilter,
+    charset: Charset,
+    options: &Options,
+    mut watcher: Option<RomWatcher>,
+    script: Option<Script>,
+) {
+    let shared = Shared::default();
+    let (reloads, reloaded) = mpsc::channel();
+
+    let logic = start_logic_thread(chip, options, shared.clone(), reloaded, script);
+
+    let mut out = stdout();
+    let terminal_guard = TerminalGuard::enter();
+
+    let mut last_pressed = [None; 16];
+    let mut last_frame = Instant::now();
+    let mut last_screen = Vec::new();
+    let mut last_size = (Chip8::SCREEN_WIDTH, Chip8::SCREEN_HEIGHT);
+    let mut frame: u64 = 0;
+    let mut recording = Recording::start_with_status_line(options);
+    let mut status = String::new();
+    let mut status_changed = false;
+
+    'running: loop {
+        let before_cycle = Instant::now();
+        let mut screenshot_requested = options.screenshot_at_frame == Some(frame);
+
+        if shared.quit.load(Ordering::Relaxed) {
+            break 'running;
+        }
+
+        while event::poll(Duration::from_secs(0)).unwrap() {
+            if let Event::Key(key) = event::read().unwrap() {
+                match key {
+                    KeyEvent {
+                        code: KeyCode::Esc, ..
+                    } => break 'running,
+                    KeyEvent {
+                        code: KeyCode::Char('c'),
+                        modifiers,
+                    } if modifiers.contains(KeyModifiers::CONTROL) => break 'running,
+                    KeyEvent {
+                        code: KeyCode::F(2),
+                        ..
+                    } => filter.cycle_mode(),
+                    KeyEvent {
+                        code: KeyCode::F(12),
+                        ..
+                    } => screenshot_requested = true,
+                    KeyEvent {
+                        code: KeyCode::F(9),
+                        ..
+                    } => recording.toggle_gif(options, frame),
+                    KeyEvent {
+                        code: KeyCode::F(7),
+                        ..
+                    } => {
+                        terminal::disable_raw_mode().unwrap();
+                        save_reports(options);
+                        terminal::enable_raw_mode().unwrap();
+                    }
+                    KeyEvent {
+                        code: KeyCode::Char(c),
+                        ..
+                    } => {
+                        if let Some(index) = options.keymap.key_index(c) {
+                            last_pressed[index] = Some(Instant::now());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut key_pressed = [false; 16];
+        for (pressed, last) in key_pressed.iter_mut().zip(last_pressed.iter()) {
+            *pressed = last.is_some_and(|time: Instant| time.elapsed() < KEY_HOLD_TIME);
+        }
+        *shared.keys.lock().unwrap() = key_pressed;
+
+        //The messages of the reload end up on the screen, so all of it gets drawn again
+        if let Some(reload) = watcher.as_mut().and_then(RomWatcher::poll) {
+            reloads.send(reload).unwrap();
+            last_screen.clear();
+            status_changed = true;
+        }
+
+        let current_gfx = *shared.gfx.lock().unwrap();
+        let intensities = filter.apply(&current_gfx, last_frame.elapsed());
+        last_frame = Instant::now();
+
+        //A smaller resolution doesn't cover all of the last screen
+        let size = (current_gfx.width(), current_gfx.height());
+        if size != last_size {
+            queue!(out, Clear(ClearType::All)).unwrap();
+            last_screen.clear();
+            status_changed = true;
+            last_size = size;
+        }
+
+        //Only redraw on change, every byte counts over a slow ssh connection
+        let screen = match charset {
+            Charset::HalfBlock => render_half_blocks(&intensities, &options.palette),
+            Charset::Braille => render_braille(&intensities, &options.palette),
+        };
+        if screen != last_screen {
+            out.write_all(&screen).unwrap();
+            queue!(out, ResetColor).unwrap();
+            out.flush().unwrap();
+            last_screen = screen;
+        }
+
+        //The terminal has no scale of its own, so screenshots are taken at 1x
+        let mut messages = recording.take_status();
+        if screenshot_requested {
+            messages.push(take_screenshot(options, frame, &current_gfx, 1));
+        }
+        recording.add_frame(&current_gfx, shared.sound.load(Ordering::Relaxed));
+        messages.extend(recording.take_status());
+        if let Some(message) = messages.pop() {
+            status = message;
+            status_changed = true;
+        }
+
+        //Status messages go on the line below the screen, printing them would scroll the screen away
+        if status_changed {
+            let row = size.1 / charset.pixels_per_row();
+            queue!(
+                out,
+                MoveTo(0, row as u16),
+                ResetColor,
+                Clear(ClearType::CurrentLine),
+                Print(&status)
+            )
+            .unwrap();
+            out.flush().unwrap();
+            status_changed = false;
+        }
+        frame += 1;
+
+        thread::sleep(
+            FRAME_TIME
+                .checked_sub(before_cycle.elapsed())
+                .unwrap_or_default(),
+        );
+    }
+
+    drop(terminal_guard);
+    stop_logic_thread(&shared, reloads, logic);
+    recording.finish();
+    save_reports(options);
+}
+
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+//Each cell shows two pixels, the upper half block takes the top one as foreground
+//and the bottom one as background colour
+fn render_half_blocks(intensities: &Intensities, palette: &Palette) -> Vec<u8> {
+    let mut screen = Vec::new();
+
+    for (row, rows) in intensities.chunks(2).enumerate() {
+        queue!(screen, MoveTo(0, row as u16)).unwrap();
+        for x in 0..rows[0].len() {
+            let top = rows[0][x];
+            let bottom = rows.get(1).map_or(0, |r| r[x]);
+            queue!(
+                screen,
+                SetForegroundColor(rgb(palette.colour(top))),
+                SetBackgroundColor(rgb(palette.colour(bottom))),
+                Print('\u{2580}')
+            )
+            .unwrap();
+        }
+    }
+
+    screen
+}
+
+//Each cell shows a 2x4 block of pixels as braille dots, which can only be on or off
+fn render_braille(intensities: &Intensities, palette: &Palette) -> Vec<u8> {
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let mut screen = Vec::new();
+
+    queue!(
+        screen,
+        SetForegroundColor(rgb(palette.foreground)),
+        SetBackgroundColor(rgb(palette.background))
+    )
+    .unwrap();
+
+    for (row, rows) in intensities.chunks(4).enumerate() {
+        queue!(screen, MoveTo(0, row as u16)).unwrap();
+        for column in 0..rows[0].len() / 2 {
+            let mut pattern = 0;
+            for (dot_row, pixels) in rows.iter().enumerate() {
+                for (dot_column, &dot) in DOTS[dot_row].iter().enumerate() {
+                    if pixels[column * 2 + dot_column] >= 128 {
+                        pattern |= dot;
+                    }
+                }
+            }
+            queue!(
+                screen,
+                Print(std::char::from_u32(0x2800 + pattern).unwrap())
+            )
+            .unwrap();
+        }
+    }
+
+    screen
+}
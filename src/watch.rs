@@ -0,0 +1,158 @@
+use crate::chip8::{Cheats, Chip8, Font, Machine, Quirks, Symbols, Timing};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+//Everything a chip gets configured with besides its ROM, to configure a rebuilt ROM the same way
+#[derive(Clone)]
+pub struct ChipSetup {
+    pub scale: u32,
+    pub machine: Machine,
+    pub quirks: Quirks,
+    pub timing: Timing,
+    pub font: Option<(Font, u16)>,
+    pub seed: u64,
+    pub symbols: Option<PathBuf>,
+    //Addresses or labels, the labels may have moved in a rebuilt ROM
+    pub breakpoints: Vec<String>,
+    pub cheat_directory: PathBuf,
+}
+
+impl ChipSetup {
+    pub fn load(&self, rom: &Path) -> Result<Chip8, String> {
+        let file =
+            File::open(rom).map_err(|e| format!("Could not open {}: {}", rom.display(), e))?;
+        let mut chip = Chip8::create_chip(file, self.scale, self.machine.clone())
+            .map_err(|e| format!("Could not load {}: {}", rom.display(), e))?;
+        self.apply(&mut chip)?;
+        Ok(chip)
+    }
+
+    pub fn apply(&self, chip: &mut Chip8) -> Result<(), String> {
+        chip.quirks = self.quirks;
+        chip.timing = self.timing;
+        if let Some((font, address)) = &self.font {
+            chip.load_font(font, *address)
+                .map_err(|e| format!("Could not load font: {}", e))?;
+        }
+        chip.seed_rng(self.seed);
+
+        if let Some(path) = &self.symbols {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Could not read symbols {}: {}", path.display(), e))?;
+            chip.symbols = Symbols::parse(&text)
+                .map_err(|e| format!("Invalid symbols {}: {}", path.display(), e))?;
+            println!(
+                "Loaded {} symbols from {}",
+                chip.symbols.len(),
+                path.display()
+            );
+        }
+        //A rebuilt ROM has a new hash and with it other cheats
+        let path = cheat_file(&self.cheat_directory, &chip.rom_hash);
+        if path.is_file() {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Could not read cheats {}: {}", path.display(), e))?;
+            chip.cheats = Cheats::parse(&text)
+                .map_err(|e| format!("Invalid cheats {}: {}", path.display(), e))?;
+            println!(
+                "Loaded {} cheats from {}",
+                chip.cheats.len(),
+                path.display()
+            );
+        }
+        for location in &self.breakpoints {
+            let address = chip
+                .symbols
+                .locate(location)
+                .ok_or_else(|| format!("Unknown breakpoint location {}", location))?;
+            chip.breakpoints.insert(address);
+        }
+        Ok(())
+    }
+}
+
+//A rebuilt ROM on its way to the thread that runs the chip
+pub struct Reload {
+    pub chip: Chip8,
+    pub restore: bool,
+}
+
+impl Reload {
+    //Tracing, profiling and coverage carry on with the new chip
+    pub fn apply(mut self, chip: &mut Chip8) {
+        self.chip.tracer = chip.tracer.take();
+        self.chip.coverage = chip.coverage.take();
+        if self.restore {
+            if self.chip.restore_state(&chip.save_state()) {
+                println!("Restored the previous state");
+            } else {
+                println!("The layout of the ROM changed, starting it from the beginning");
+            }
+        }
+        *chip = self.chip;
+    }
+}
+
+//Loads the ROM again whenever its modification time changes
+pub struct RomWatcher {
+    rom: PathBuf,
+    setup: ChipSetup,
+    restore: bool,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl RomWatcher {
+    pub fn new(rom: &Path, setup: ChipSetup, restore: bool) -> RomWatcher {
+        println!("Watching {} for changes", rom.display());
+        RomWatcher {
+            rom: rom.to_path_buf(),
+            setup,
+            restore,
+            modified: modified(rom),
+            last_poll: Instant::now(),
+        }
+    }
+
+    //Called every frame, only looks at the file every POLL_INTERVAL
+    pub fn poll(&mut self) -> Option<Reload> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = modified(&self.rom);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        //An assembler that is still writing leaves a broken ROM, the next write loads it again
+        match self.setup.load(&self.rom) {
+            Ok(chip) => {
+                println!("Reloaded {}", self.rom.display());
+                Some(Reload {
+                    chip,
+                    restore: self.restore,
+                })
+            }
+            Err(e) => {
+                println!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+pub fn cheat_file(directory: &Path, rom_hash: &str) -> PathBuf {
+    directory.join(format!("{}.txt", rom_hash))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}